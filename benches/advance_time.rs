@@ -0,0 +1,28 @@
+//! Times `advance_time_by` over growing gaps. With the `BTreeMap` schedule the
+//! cost tracks the number of buckets drained, not the number of ticks skipped.
+//!
+//! Run with `cargo bench --bench advance_time`.
+use lease_cache_sim::LeaseCache;
+use std::time::Instant;
+
+const NUM_OBJS: usize = 10000;
+
+fn main() {
+    for gap in [10_000usize, 10_000_000, 10_000_000_000, 10_000_000_000_000] {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        //spread the objects evenly over the gap so every run drains the same amount
+        for obj_id in 0..NUM_OBJS {
+            lease_cache.insert(obj_id, (obj_id + 1) * (gap / NUM_OBJS));
+        }
+        let start = Instant::now();
+        let expired = lease_cache.advance_time_by(gap);
+        let elapsed = start.elapsed();
+        assert_eq!(expired.len(), NUM_OBJS);
+        println!(
+            "advance_time_by({:>16}) drained {:>6} objects in {:?}",
+            gap,
+            expired.len(),
+            elapsed
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Replays the same integer trace through a `LeaseCache` hashing with the
+//! default SipHash and with a multiplicative hasher. On integer object ids
+//! the cheaper hasher dominates the per-access cost.
+//!
+//! Run with `cargo bench --bench hasher`.
+use abstract_cache::CacheSim;
+use lease_cache_sim::{CacheHasher, LeaseCache, TaggedObjectId};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::time::Instant;
+
+const NUM_ACCESSES: usize = 2_000_000;
+const NUM_OBJS: usize = 10_000;
+
+//multiplies the key by a large odd constant, good enough for integer ids
+#[derive(Default)]
+struct IntHasher(u64);
+
+impl Hasher for IntHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0.rotate_left(8) ^ byte as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        }
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = (self.0 ^ i).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+fn replay<S: CacheHasher>(name: &str, trace: &[TaggedObjectId<usize, usize>]) {
+    let mut lease_cache = LeaseCache::<usize, usize, S>::with_rng_and_hasher(0, S::default());
+    lease_cache.set_capacity(NUM_OBJS);
+    let start = Instant::now();
+    for access in trace {
+        lease_cache.cache_access(access.clone());
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:>12}: {:>8.0} accesses/ms ({} hits in {:?})",
+        name,
+        trace.len() as f64 / elapsed.as_secs_f64() / 1000.0,
+        lease_cache.hit_count(),
+        elapsed
+    );
+}
+
+fn main() {
+    //a strided walk over the objects with mixed leases
+    let trace: Vec<TaggedObjectId<usize, usize>> = (0..NUM_ACCESSES)
+        .map(|i| TaggedObjectId(1 + i % 97 * 200, i * 7919 % NUM_OBJS))
+        .collect();
+    replay::<RandomState>("RandomState", &trace);
+    replay::<BuildHasherDefault<IntHasher>>("IntHasher", &trace);
+}
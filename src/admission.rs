@@ -0,0 +1,73 @@
+use abstract_cache::ObjIdTraits;
+
+use crate::Time;
+
+/// Decides whether an object that missed is brought into a [`LeaseCache`]
+/// at all. Install one with [`LeaseCache::set_admission_policy`]; without
+/// one every missed object is admitted.
+///
+/// [`LeaseCache`]: crate::LeaseCache
+/// [`LeaseCache::set_admission_policy`]: crate::LeaseCache::set_admission_policy
+pub trait AdmissionPolicy<Obj: ObjIdTraits, T: Time = usize>: AdmissionPolicyClone<Obj, T> {
+    /// Whether `obj_id`, which just missed, is cached with `lease`. A
+    /// rejected access still counts as a miss.
+    fn admit(&mut self, obj_id: &Obj, lease: T) -> bool;
+}
+
+/// Lets a cache holding a boxed [`AdmissionPolicy`] be cloned. Implemented
+/// for every policy that is `Clone`.
+pub trait AdmissionPolicyClone<Obj: ObjIdTraits, T: Time> {
+    fn clone_box(&self) -> Box<dyn AdmissionPolicy<Obj, T>>;
+}
+
+impl<Obj: ObjIdTraits, T: Time, P: AdmissionPolicy<Obj, T> + Clone + 'static>
+    AdmissionPolicyClone<Obj, T> for P
+{
+    fn clone_box(&self) -> Box<dyn AdmissionPolicy<Obj, T>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Admits every object, like a cache without a policy.
+#[derive(Debug, Clone, Default)]
+pub struct AlwaysAdmit;
+
+impl<Obj: ObjIdTraits, T: Time> AdmissionPolicy<Obj, T> for AlwaysAdmit {
+    fn admit(&mut self, _obj_id: &Obj, _lease: T) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{LeaseCache, TaggedObjectId};
+    use abstract_cache::{AccessResult, CacheSim};
+
+    //turns away a single object
+    #[derive(Clone)]
+    struct Reject(usize);
+
+    impl AdmissionPolicy<usize> for Reject {
+        fn admit(&mut self, obj_id: &usize, _lease: usize) -> bool {
+            *obj_id != self.0
+        }
+    }
+
+    #[test]
+    fn test_rejected_object_never_cached() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_admission_policy(Reject(2));
+        for obj_id in [1, 2, 1, 2, 2] {
+            lease_cache.cache_access(TaggedObjectId(10, obj_id));
+            assert!(!lease_cache.contains(&2));
+        }
+        assert_eq!(lease_cache.hit_count(), 1);
+        assert_eq!(lease_cache.miss_count(), 4);
+        //the policy travels with clones
+        assert_eq!(lease_cache.clone().update(&2, 10), AccessResult::Miss);
+        lease_cache.set_admission_policy(AlwaysAdmit);
+        lease_cache.cache_access(TaggedObjectId(10, 2));
+        assert!(lease_cache.contains(&2));
+    }
+}
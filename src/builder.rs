@@ -0,0 +1,145 @@
+use abstract_cache::ObjIdTraits;
+use std::collections::hash_map::RandomState;
+
+use crate::{AdmissionPolicy, CacheHasher, ClockMode, EvictionPolicy, LeaseCache, Time};
+
+/// Collects the configuration of a [`LeaseCache`] and creates it in one go.
+/// Options left unset keep the defaults of [`LeaseCache::new`].
+pub struct LeaseCacheBuilder<Obj: ObjIdTraits, T: Time = usize, S = RandomState> {
+    capacity: Option<usize>,
+    seed: Option<u64>,
+    clock_mode: ClockMode,
+    start_time: T,
+    eviction_policy: Option<Box<dyn EvictionPolicy<Obj, T, S>>>,
+    admission_policy: Option<Box<dyn AdmissionPolicy<Obj, T>>>,
+    track_stats: bool,
+    track_frequency: bool,
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> LeaseCacheBuilder<Obj, T, S> {
+    pub fn new() -> Self {
+        LeaseCacheBuilder {
+            capacity: None,
+            seed: None,
+            clock_mode: ClockMode::PerAccess,
+            start_time: T::ZERO,
+            eviction_policy: None,
+            admission_policy: None,
+            track_stats: true,
+            track_frequency: false,
+        }
+    }
+
+    /// See [`CacheSim::set_capacity`](abstract_cache::CacheSim::set_capacity).
+    pub fn capacity(mut self, cache_size: usize) -> Self {
+        self.capacity = Some(cache_size);
+        self
+    }
+
+    /// Seeds the eviction RNG, as [`LeaseCache::with_rng`] does.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// See [`LeaseCache::with_clock_mode`].
+    pub fn clock_mode(mut self, clock_mode: ClockMode) -> Self {
+        self.clock_mode = clock_mode;
+        self
+    }
+
+    /// See [`LeaseCache::with_start_time`].
+    pub fn start_time(mut self, start_time: T) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    /// See [`LeaseCache::set_eviction_policy`].
+    pub fn eviction_policy<P: EvictionPolicy<Obj, T, S> + 'static>(mut self, policy: P) -> Self {
+        self.eviction_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// See [`LeaseCache::set_admission_policy`].
+    pub fn admission_policy<P: AdmissionPolicy<Obj, T> + 'static>(mut self, policy: P) -> Self {
+        self.admission_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// See [`LeaseCache::set_track_stats`].
+    pub fn track_stats(mut self, track_stats: bool) -> Self {
+        self.track_stats = track_stats;
+        self
+    }
+
+    /// See [`LeaseCache::set_track_frequency`].
+    pub fn track_frequency(mut self, track_frequency: bool) -> Self {
+        self.track_frequency = track_frequency;
+        self
+    }
+
+    pub fn build(self) -> LeaseCache<Obj, T, S> {
+        let mut lease_cache = match self.seed {
+            Some(seed) => LeaseCache::with_rng_and_hasher(seed, S::default()),
+            None => LeaseCache::default(),
+        };
+        lease_cache.cache_size = self.capacity;
+        lease_cache.clock_mode = self.clock_mode;
+        lease_cache.current_time = self.start_time;
+        //the cache starts empty, so the policy has nothing to catch up on
+        lease_cache.eviction_policy = self.eviction_policy;
+        lease_cache.admission_policy = self.admission_policy;
+        lease_cache.set_track_stats(self.track_stats);
+        lease_cache.set_track_frequency(self.track_frequency);
+        lease_cache
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> Default for LeaseCacheBuilder<Obj, T, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EarliestExpiry, TaggedObjectId};
+    use abstract_cache::CacheSim;
+
+    #[test]
+    fn test_builder_options() {
+        let mut lease_cache = LeaseCacheBuilder::<usize>::new()
+            .capacity(2)
+            .seed(5)
+            .eviction_policy(EarliestExpiry)
+            .track_stats(false)
+            .track_frequency(true)
+            .build();
+        assert_eq!(lease_cache.cache_size, Some(2));
+        for (lease, obj_id) in [(10, 1), (3, 2), (10, 1), (10, 3)] {
+            lease_cache.cache_access(TaggedObjectId(lease, obj_id));
+        }
+        //the earliest-expiry policy picked 2 over the longer-leased 1
+        assert_eq!(lease_cache.last_evicted(), Some(&2));
+        assert_eq!(lease_cache.access_count(), 0);
+        assert_eq!(lease_cache.access_frequency(&1), Some(2));
+        assert_eq!(lease_cache.access_frequency(&4), Some(0));
+
+        //the seed drives random evictions the same way as with_rng
+        let victims = |mut lease_cache: LeaseCache<usize>| {
+            lease_cache.insert_many((0..20).map(|obj_id| (obj_id, 5)));
+            lease_cache.set_capacity_now(5)
+        };
+        assert_eq!(
+            victims(LeaseCacheBuilder::new().seed(9).build()),
+            victims(LeaseCache::with_rng(9))
+        );
+        let default_cache = LeaseCacheBuilder::<usize>::new().build();
+        assert_eq!(default_cache.cache_size, None);
+        assert_eq!(default_cache.access_frequency(&1), None);
+        assert_eq!(default_cache.current_time(), 0);
+        let started = LeaseCacheBuilder::<usize>::new().start_time(100).build();
+        assert_eq!(started.current_time(), 100);
+    }
+}
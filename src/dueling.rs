@@ -0,0 +1,160 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::{LeaseCache, Occupancy, TaggedObjectId};
+
+/// The two lease choices a [`DuelingLeaseCache`] decides between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeasePolicy {
+    Short,
+    Long,
+}
+
+/// Set dueling between a short and a long lease. Objects hash into
+/// `num_sets` sets: the first `leader_sets` always get the short lease, the
+/// next `leader_sets` always get the long lease, and every other set follows
+/// whichever policy has missed less in its leaders lately. All sets share
+/// one [`LeaseCache`] and its capacity.
+///
+/// Leader misses move a saturating selector, as in set-dueling replacement
+/// policies, so the winner can change when the program's behaviour does.
+#[derive(Clone)]
+pub struct DuelingLeaseCache<Obj: ObjIdTraits> {
+    cache: LeaseCache<Obj>,
+    short_lease: usize,
+    long_lease: usize,
+    num_sets: usize,
+    leader_sets: usize,
+    //positive once the short leaders have missed more than the long ones
+    selector: i64,
+    short_leader_misses: u64,
+    long_leader_misses: u64,
+}
+
+//bound on the selector, so a long-gone phase cannot outvote the current one forever
+const SELECTOR_MAX: i64 = 1024;
+
+impl<Obj: ObjIdTraits> DuelingLeaseCache<Obj> {
+    pub fn new(short_lease: usize, long_lease: usize, num_sets: usize, leader_sets: usize) -> Self {
+        assert!(
+            leader_sets > 0 && 2 * leader_sets <= num_sets,
+            "need leader sets for both policies"
+        );
+        DuelingLeaseCache {
+            cache: LeaseCache::new(),
+            short_lease,
+            long_lease,
+            num_sets,
+            leader_sets,
+            selector: 0,
+            short_leader_misses: 0,
+            long_leader_misses: 0,
+        }
+    }
+
+    /// The policy the follower sets use right now. Ties go to the short
+    /// lease, which costs less cache space.
+    pub fn winning_policy(&self) -> LeasePolicy {
+        if self.selector > 0 {
+            LeasePolicy::Long
+        } else {
+            LeasePolicy::Short
+        }
+    }
+
+    /// Misses seen in the short-lease and long-lease leader sets.
+    pub fn leader_misses(&self) -> (u64, u64) {
+        (self.short_leader_misses, self.long_leader_misses)
+    }
+
+    /// The policy `obj_id` is cached under: fixed for leader sets, the
+    /// current winner for the rest.
+    pub fn policy_for(&self, obj_id: &Obj) -> LeasePolicy {
+        self.leader_policy(obj_id)
+            .unwrap_or_else(|| self.winning_policy())
+    }
+
+    /// The underlying cache, e.g. for reading its hit/miss counters.
+    pub fn cache(&self) -> &LeaseCache<Obj> {
+        &self.cache
+    }
+
+    //the fixed policy of obj_id's set, None for follower sets
+    fn leader_policy(&self, obj_id: &Obj) -> Option<LeasePolicy> {
+        let mut hasher = DefaultHasher::new();
+        obj_id.hash(&mut hasher);
+        let set = (hasher.finish() % self.num_sets as u64) as usize;
+        if set < self.leader_sets {
+            Some(LeasePolicy::Short)
+        } else if set < 2 * self.leader_sets {
+            Some(LeasePolicy::Long)
+        } else {
+            None
+        }
+    }
+}
+
+impl<Obj: ObjIdTraits> CacheSim<Obj> for DuelingLeaseCache<Obj> {
+    fn cache_access(&mut self, obj_id: Obj) -> AccessResult {
+        let leader = self.leader_policy(&obj_id);
+        let lease = match self.policy_for(&obj_id) {
+            LeasePolicy::Short => self.short_lease,
+            LeasePolicy::Long => self.long_lease,
+        };
+        let cache_result = self.cache.cache_access(TaggedObjectId(lease, obj_id));
+        if let AccessResult::Miss = cache_result {
+            match leader {
+                Some(LeasePolicy::Short) => {
+                    self.short_leader_misses += 1;
+                    self.selector = (self.selector + 1).min(SELECTOR_MAX);
+                }
+                Some(LeasePolicy::Long) => {
+                    self.long_leader_misses += 1;
+                    self.selector = (self.selector - 1).max(-SELECTOR_MAX);
+                }
+                None => {}
+            }
+        }
+        cache_result
+    }
+
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.cache.set_capacity(cache_size);
+        self
+    }
+}
+
+impl<Obj: ObjIdTraits> Occupancy for DuelingLeaseCache<Obj> {
+    fn occupancy(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_long_lease_wins_on_long_reuse() {
+        //objects come back every 64 accesses, which only the long lease covers
+        let mut lease_cache = DuelingLeaseCache::<usize>::new(4, 100, 8, 1);
+        for i in 0..64 * 20 {
+            lease_cache.cache_access(i % 64);
+        }
+        assert_eq!(lease_cache.winning_policy(), LeasePolicy::Long);
+        let (short_misses, long_misses) = lease_cache.leader_misses();
+        assert!(short_misses > long_misses);
+        //followers have switched over and now hit
+        let follower = (0..64)
+            .find(|obj_id| lease_cache.leader_policy(obj_id).is_none())
+            .unwrap();
+        assert_eq!(lease_cache.policy_for(&follower), LeasePolicy::Long);
+        assert!(matches!(
+            lease_cache.cache_access(follower),
+            AccessResult::Hit
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use abstract_cache::ObjIdTraits;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{CacheHasher, LeaseCache, Time};
+
+/// Picks the victims a [`LeaseCache`] force-evicts when it is over capacity.
+/// Install one with [`LeaseCache::set_eviction_policy`]; without one the
+/// cache evicts uniformly at random using its own RNG.
+///
+/// The `on_*` hooks let a policy keep its own bookkeeping in step with the
+/// cache contents. They default to doing nothing.
+pub trait EvictionPolicy<Obj: ObjIdTraits, T: Time = usize, S = RandomState>:
+    EvictionPolicyClone<Obj, T, S>
+{
+    /// The object to evict next. Returning None means there is nothing the
+    /// policy is willing to evict, so the cache evicts nothing; returning an
+    /// object that is not in the cache makes it fall back to a random victim.
+    fn choose_victim(&mut self, cache: &LeaseCache<Obj, T, S>) -> Option<Obj>;
+
+    /// `obj_id` entered the cache. Renewing the lease of an object that is
+    /// already cached does not count.
+    fn on_insert(&mut self, _obj_id: &Obj) {}
+
+    /// `obj_id` left the cache, for whatever reason.
+    fn on_remove(&mut self, _obj_id: &Obj) {}
+
+    /// The cache was emptied at once, e.g. by [`LeaseCache::clear`].
+    fn on_clear(&mut self) {}
+}
+
+/// Lets a cache holding a boxed [`EvictionPolicy`] be cloned. Implemented
+/// for every policy that is `Clone`.
+pub trait EvictionPolicyClone<Obj: ObjIdTraits, T: Time, S> {
+    fn clone_box(&self) -> Box<dyn EvictionPolicy<Obj, T, S>>;
+}
+
+impl<Obj: ObjIdTraits, T: Time, S, P: EvictionPolicy<Obj, T, S> + Clone + 'static>
+    EvictionPolicyClone<Obj, T, S> for P
+{
+    fn clone_box(&self) -> Box<dyn EvictionPolicy<Obj, T, S>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Evicts a uniformly random object, like a cache without a policy, but
+/// from an RNG of its own.
+#[derive(Debug, Clone)]
+pub struct RandomEviction {
+    rng: StdRng,
+}
+
+impl RandomEviction {
+    pub fn new() -> Self {
+        RandomEviction {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_rng(seed: u64) -> Self {
+        RandomEviction {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for RandomEviction {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> EvictionPolicy<Obj, T, S> for RandomEviction {
+    fn choose_victim(&mut self, cache: &LeaseCache<Obj, T, S>) -> Option<Obj> {
+        cache.live_objects.random(&mut self.rng).cloned()
+    }
+}
+
+/// Evicts the object whose lease runs out soonest, with the same tie-break
+/// as [`LeaseCache::evict_earliest`].
+#[derive(Debug, Clone, Default)]
+pub struct EarliestExpiry;
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> EvictionPolicy<Obj, T, S> for EarliestExpiry {
+    fn choose_victim(&mut self, cache: &LeaseCache<Obj, T, S>) -> Option<Obj> {
+        cache.earliest_victim()
+    }
+}
+
+/// Evicts the object with the most lease left, the one furthest from
+/// needing its slot, the opposite of [`EarliestExpiry`]. Ties go the same
+/// way as for [`EarliestExpiry`]. Pinned objects are never picked, so a
+/// cache holding only pinned objects evicts nothing.
+#[derive(Debug, Clone, Default)]
+pub struct EvictLargestLease;
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> EvictionPolicy<Obj, T, S> for EvictLargestLease {
+    fn choose_victim(&mut self, cache: &LeaseCache<Obj, T, S>) -> Option<Obj> {
+        cache.latest_victim()
+    }
+}
+
+/// Evicts the object that entered the cache longest ago (FIFO). Renewing a
+/// lease does not move an object back in line.
+#[derive(Debug, Clone)]
+pub struct InsertionOrder<Obj: ObjIdTraits> {
+    //map from ObjId to its place in line
+    tickets: HashMap<Obj, u64>,
+    //the same entries ordered by ticket, oldest first
+    line: BTreeMap<u64, Obj>,
+    next_ticket: u64,
+}
+
+impl<Obj: ObjIdTraits> InsertionOrder<Obj> {
+    pub fn new() -> Self {
+        InsertionOrder {
+            tickets: HashMap::new(),
+            line: BTreeMap::new(),
+            next_ticket: 0,
+        }
+    }
+}
+
+impl<Obj: ObjIdTraits> Default for InsertionOrder<Obj> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Obj: ObjIdTraits + 'static, T: Time, S> EvictionPolicy<Obj, T, S> for InsertionOrder<Obj> {
+    fn choose_victim(&mut self, _cache: &LeaseCache<Obj, T, S>) -> Option<Obj> {
+        self.line
+            .first_key_value()
+            .map(|(_, obj_id)| obj_id.clone())
+    }
+
+    fn on_insert(&mut self, obj_id: &Obj) {
+        let ticket = self.next_ticket;
+        self.next_ticket += 1;
+        if let Some(old_ticket) = self.tickets.insert(obj_id.clone(), ticket) {
+            self.line.remove(&old_ticket);
+        }
+        self.line.insert(ticket, obj_id.clone());
+    }
+
+    fn on_remove(&mut self, obj_id: &Obj) {
+        if let Some(ticket) = self.tickets.remove(obj_id) {
+            self.line.remove(&ticket);
+        }
+    }
+
+    fn on_clear(&mut self) {
+        self.tickets.clear();
+        self.line.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TaggedObjectId;
+    use abstract_cache::{AccessResult, CacheSim};
+
+    #[test]
+    fn test_earliest_expiry_policy() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_eviction_policy(EarliestExpiry);
+        lease_cache.set_capacity(3);
+        for (lease, obj_id) in [(9, 1), (4, 2), (6, 3)] {
+            lease_cache.cache_access(TaggedObjectId(lease, obj_id));
+        }
+        let (_, victims) = lease_cache.cache_access_tracked(TaggedObjectId(20, 4));
+        assert_eq!(victims, vec![2]);
+        let (_, victims) = lease_cache.cache_access_tracked(TaggedObjectId(20, 5));
+        assert_eq!(victims, vec![3]);
+    }
+
+    #[test]
+    fn test_largest_lease_policy() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_eviction_policy(EvictLargestLease);
+        lease_cache.set_capacity(3);
+        lease_cache.insert_pinned(0);
+        for (lease, obj_id) in [(1, 1), (5, 2), (100, 3)] {
+            lease_cache.insert(obj_id, lease);
+        }
+        assert_eq!(lease_cache.force_evict(), 3);
+        assert_eq!(lease_cache.force_evict(), 2);
+        assert_eq!(lease_cache.force_evict(), 1);
+        assert_eq!(lease_cache.try_force_evict(), None);
+    }
+
+    #[test]
+    fn test_largest_lease_policy_keeps_pinned() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(3);
+        lease_cache.set_eviction_policy(EvictLargestLease);
+        lease_cache.set_capacity(2);
+        lease_cache.insert_pinned(0);
+        lease_cache.insert_pinned(1);
+        for obj_id in 2..20 {
+            lease_cache.cache_access(TaggedObjectId(obj_id, obj_id));
+        }
+        assert!(lease_cache.contains(&0) && lease_cache.contains(&1));
+        assert_eq!(lease_cache.len(), 2);
+        assert_eq!(lease_cache.pinned_count(), 2);
+    }
+
+    #[test]
+    fn test_insertion_order_policy() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 10);
+        //objects already cached join the line in their current order
+        lease_cache.set_eviction_policy(InsertionOrder::new());
+        lease_cache.insert(2, 10);
+        lease_cache.insert(3, 10);
+        //a renewal keeps 1 at the front, a removal takes 2 out of line
+        lease_cache.insert(1, 20);
+        lease_cache.remove(&2);
+        assert_eq!(lease_cache.force_evict(), 1);
+        lease_cache.insert(4, 10);
+        assert_eq!(lease_cache.clone().force_evict(), 3);
+        assert_eq!(lease_cache.force_evict(), 3);
+        assert_eq!(lease_cache.force_evict(), 4);
+    }
+
+    #[test]
+    fn test_insertion_order_hit_at_expiry_tick() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_eviction_policy(InsertionOrder::new());
+        lease_cache.update(&1, 2);
+        lease_cache.update(&2, 10);
+        //1 is reused exactly as its lease runs out and stays at the front
+        assert!(matches!(lease_cache.update(&1, 10), AccessResult::Hit));
+        assert_eq!(lease_cache.force_evict(), 1);
+    }
+
+    #[test]
+    fn test_random_policy_seeded() {
+        let victims = |seed| {
+            let mut lease_cache = LeaseCache::<usize>::new();
+            lease_cache.set_eviction_policy(RandomEviction::with_rng(seed));
+            lease_cache.insert_many((0..20).map(|obj_id| (obj_id, 5)));
+            (0..10)
+                .map(|_| lease_cache.force_evict())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(victims(3), victims(3));
+    }
+}
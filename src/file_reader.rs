@@ -0,0 +1,755 @@
+//! Readers for memory-access traces and lease tables stored as CSV.
+//!
+//! A trace has one access per line with the reference in column 0 and the
+//! accessed address in column 2, both in hex:
+//!
+//! ```text
+//! 4005d0, R, 7ffe3a10
+//! ```
+//!
+//! A lease table has one reference per line as
+//! `reference, short_lease, long_lease, short_lease_prob`, where the first
+//! three columns are hex and the probability is a decimal float:
+//!
+//! ```text
+//! 4005d0, 1a, 200, 0.75
+//! ```
+//!
+//! A trace may also carry the absolute time of each access in a column of
+//! its own, see [`TraceReaderConfig::timestamp_col`].
+//!
+//! For large traces that are replayed often there is also a binary format,
+//! read by [`read_binary_trace`] and written by
+//! [`write_binary_trace`](crate::file_writer::write_binary_trace): a plain
+//! sequence of 16-byte records, each the reference and then the address as
+//! little-endian `u64`s.
+//!
+//! Files whose name ends in `.gz` are decompressed on the fly.
+use abstract_cache::ObjIdTraits;
+use csv::StringRecord;
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::num::{ParseFloatError, ParseIntError};
+use std::path::Path;
+
+use crate::TaggedObjectId;
+
+/// Everything that can go wrong while reading a trace or lease table, or
+/// writing results with [`file_writer`](crate::file_writer).
+/// Line numbers are 1-based and refer to the input file.
+#[derive(Debug)]
+pub enum TraceError {
+    Io(std::io::Error),
+    Csv(csv::Error),
+    ParseInt {
+        line: Option<u64>,
+        source: ParseIntError,
+    },
+    ParseFloat {
+        line: Option<u64>,
+        source: ParseFloatError,
+    },
+    MissingColumn {
+        line: Option<u64>,
+        column: usize,
+    },
+    /// a lease table probability that is not a finite number in [0, 1]
+    InvalidProbability {
+        line: Option<u64>,
+        reference: u64,
+        probability: f64,
+    },
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(f, "io error: {}", err),
+            TraceError::Csv(err) => write!(f, "csv error: {}", err),
+            TraceError::ParseInt { line, source } => {
+                write!(f, "{}invalid integer: {}", LinePrefix(*line), source)
+            }
+            TraceError::ParseFloat { line, source } => {
+                write!(f, "{}invalid float: {}", LinePrefix(*line), source)
+            }
+            TraceError::MissingColumn { line, column } => {
+                write!(f, "{}missing column {}", LinePrefix(*line), column)
+            }
+            TraceError::InvalidProbability {
+                line,
+                reference,
+                probability,
+            } => write!(
+                f,
+                "{}short lease probability {} of reference {:x} is not in [0, 1]",
+                LinePrefix(*line),
+                probability,
+                reference
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TraceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TraceError::Io(err) => Some(err),
+            TraceError::Csv(err) => Some(err),
+            TraceError::ParseInt { source, .. } => Some(source),
+            TraceError::ParseFloat { source, .. } => Some(source),
+            TraceError::MissingColumn { .. } => None,
+            TraceError::InvalidProbability { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TraceError {
+    fn from(err: std::io::Error) -> Self {
+        TraceError::Io(err)
+    }
+}
+
+impl From<csv::Error> for TraceError {
+    fn from(err: csv::Error) -> Self {
+        TraceError::Csv(err)
+    }
+}
+
+impl From<ParseIntError> for TraceError {
+    fn from(source: ParseIntError) -> Self {
+        TraceError::ParseInt { line: None, source }
+    }
+}
+
+impl From<ParseFloatError> for TraceError {
+    fn from(source: ParseFloatError) -> Self {
+        TraceError::ParseFloat { line: None, source }
+    }
+}
+
+//lets parsers that cannot fail, such as str::parse::<String>, be passed to trace_to_vec
+impl From<Infallible> for TraceError {
+    fn from(err: Infallible) -> Self {
+        match err {}
+    }
+}
+
+//renders "line N: " when the line is known
+struct LinePrefix(Option<u64>);
+impl Display for LinePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(line) => write!(f, "line {}: ", line),
+            None => Ok(()),
+        }
+    }
+}
+
+fn line_of(record: &StringRecord) -> Option<u64> {
+    record.position().map(|position| position.line())
+}
+
+fn field(record: &StringRecord, column: usize) -> Result<&str, TraceError> {
+    record.get(column).ok_or(TraceError::MissingColumn {
+        line: line_of(record),
+        column,
+    })
+}
+
+//parses one column with parse, filling in the line of errors that lack one
+fn parse_field<V, E: Into<TraceError>>(
+    record: &StringRecord,
+    column: usize,
+    parse: impl Fn(&str) -> Result<V, E>,
+) -> Result<V, TraceError> {
+    parse(field(record, column)?).map_err(|err| match err.into() {
+        TraceError::ParseInt { line: None, source } => TraceError::ParseInt {
+            line: line_of(record),
+            source,
+        },
+        TraceError::ParseFloat { line: None, source } => TraceError::ParseFloat {
+            line: line_of(record),
+            source,
+        },
+        err => err,
+    })
+}
+
+fn parse_u64(record: &StringRecord, column: usize, radix: u32) -> Result<u64, TraceError> {
+    parse_field(record, column, |text| u64::from_str_radix(text, radix))
+}
+
+fn parse_usize(record: &StringRecord, column: usize, radix: u32) -> Result<usize, TraceError> {
+    parse_field(record, column, |text| usize::from_str_radix(text, radix))
+}
+
+fn float(record: &StringRecord, column: usize) -> Result<f64, TraceError> {
+    field(record, column)?
+        .parse()
+        .map_err(|source| TraceError::ParseFloat {
+            line: line_of(record),
+            source,
+        })
+}
+
+//opens path, transparently decompressing it if the name ends in .gz
+fn open(path: impl AsRef<Path>) -> Result<Box<dyn Read>, TraceError> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        return Ok(Box::new(GzDecoder::new(file)));
+    }
+    Ok(Box::new(file))
+}
+
+fn csv_reader<R: Read>(reader: R, has_headers: bool, delimiter: u8) -> csv::Reader<R> {
+    csv::ReaderBuilder::new()
+        .has_headers(has_headers)
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(reader)
+}
+
+/// Describes the layout of a trace file. The default matches the format
+/// described in the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceReaderConfig {
+    /// column holding the reference
+    pub ref_col: usize,
+    /// column holding the accessed address
+    pub addr_col: usize,
+    /// column holding the timestamp of each access, read by
+    /// [`timed_trace_to_vec_with_config`] and ignored by the other readers
+    pub timestamp_col: Option<usize>,
+    /// radix of the reference, the address and the timestamp
+    pub radix: u32,
+    /// skip the first line as a header row
+    pub has_headers: bool,
+    pub delimiter: u8,
+}
+
+impl Default for TraceReaderConfig {
+    fn default() -> Self {
+        TraceReaderConfig {
+            ref_col: 0,
+            addr_col: 2,
+            timestamp_col: None,
+            radix: 16,
+            has_headers: false,
+            delimiter: b',',
+        }
+    }
+}
+
+/// Reads a trace into `TaggedObjectId(reference, address)` pairs, turning
+/// the reference and address columns into `Tag` and `Obj` with `parse_tag`
+/// and `parse_obj`, e.g. `str::parse::<String>` for traces of symbol names.
+/// The layout comes from `config`, except for its radix, which is up to the
+/// parsers.
+pub fn trace_to_vec<Tag, Obj, TagErr, ObjErr>(
+    path: impl AsRef<Path>,
+    config: &TraceReaderConfig,
+    parse_tag: impl Fn(&str) -> Result<Tag, TagErr>,
+    parse_obj: impl Fn(&str) -> Result<Obj, ObjErr>,
+) -> Result<Vec<TaggedObjectId<Tag, Obj>>, TraceError>
+where
+    Tag: ObjIdTraits,
+    Obj: ObjIdTraits,
+    TagErr: Into<TraceError>,
+    ObjErr: Into<TraceError>,
+{
+    parsed_records(open(path)?, config.clone(), parse_tag, parse_obj).collect()
+}
+
+/// Reads a trace into `TaggedObjectId(reference, address)` pairs.
+pub fn trace_to_vec_u64(
+    path: impl AsRef<Path>,
+) -> Result<Vec<TaggedObjectId<u64, u64>>, TraceError> {
+    trace_to_vec_with_config(path, &TraceReaderConfig::default())
+}
+
+/// Like [`trace_to_vec_u64`], but with the column layout, radix, header
+/// row and delimiter taken from `config`.
+pub fn trace_to_vec_with_config(
+    path: impl AsRef<Path>,
+    config: &TraceReaderConfig,
+) -> Result<Vec<TaggedObjectId<u64, u64>>, TraceError> {
+    let parse = |text: &str| u64::from_str_radix(text, config.radix);
+    trace_to_vec(path, config, parse, parse)
+}
+
+/// Like [`trace_to_vec_u64`], but always treats the file as gzip-compressed
+/// regardless of its name.
+pub fn trace_to_vec_u64_gz(
+    path: impl AsRef<Path>,
+) -> Result<Vec<TaggedObjectId<u64, u64>>, TraceError> {
+    read_trace(
+        GzDecoder::new(File::open(path)?),
+        &TraceReaderConfig::default(),
+    )
+}
+
+fn read_trace<R: Read>(
+    reader: R,
+    config: &TraceReaderConfig,
+) -> Result<Vec<TaggedObjectId<u64, u64>>, TraceError> {
+    trace_records(reader, config.clone()).collect()
+}
+
+//lazily parses each record of reader as one access
+fn trace_records<R: Read>(
+    reader: R,
+    config: TraceReaderConfig,
+) -> impl Iterator<Item = Result<TaggedObjectId<u64, u64>, TraceError>> {
+    let radix = config.radix;
+    let parse = move |text: &str| u64::from_str_radix(text, radix);
+    parsed_records(reader, config, parse, parse)
+}
+
+fn parsed_records<R: Read, Tag, Obj, TagErr, ObjErr>(
+    reader: R,
+    config: TraceReaderConfig,
+    parse_tag: impl Fn(&str) -> Result<Tag, TagErr>,
+    parse_obj: impl Fn(&str) -> Result<Obj, ObjErr>,
+) -> impl Iterator<Item = Result<TaggedObjectId<Tag, Obj>, TraceError>>
+where
+    Tag: ObjIdTraits,
+    Obj: ObjIdTraits,
+    TagErr: Into<TraceError>,
+    ObjErr: Into<TraceError>,
+{
+    csv_reader(reader, config.has_headers, config.delimiter)
+        .into_records()
+        .map(move |record| {
+            let record = record?;
+            Ok(TaggedObjectId(
+                parse_field(&record, config.ref_col, &parse_tag)?,
+                parse_field(&record, config.addr_col, &parse_obj)?,
+            ))
+        })
+}
+
+/// Streams a trace one access at a time instead of loading it into memory,
+/// so traces of any size can be fed straight into `cache_access`.
+/// Failing to open the file shows up as a single `Err` item.
+pub fn trace_iter(
+    path: impl AsRef<Path>,
+) -> impl Iterator<Item = Result<TaggedObjectId<u64, u64>, TraceError>> {
+    trace_iter_with_config(path, TraceReaderConfig::default())
+}
+
+/// Like [`trace_iter`], with the layout taken from `config`.
+pub fn trace_iter_with_config(
+    path: impl AsRef<Path>,
+    config: TraceReaderConfig,
+) -> impl Iterator<Item = Result<TaggedObjectId<u64, u64>, TraceError>> {
+    let (records, open_error) = match open(path) {
+        Ok(reader) => (Some(trace_records(reader, config)), None),
+        Err(err) => (None, Some(Err(err))),
+    };
+    open_error.into_iter().chain(records.into_iter().flatten())
+}
+
+/// Streams a trace in the binary format described in the
+/// [module docs](self). A trailing partial record is reported as an
+/// [`io::ErrorKind::UnexpectedEof`] error. Failing to open the file shows
+/// up as a single `Err` item, as for [`trace_iter`].
+pub fn read_binary_trace(
+    path: impl AsRef<Path>,
+) -> impl Iterator<Item = Result<TaggedObjectId<u64, u64>, TraceError>> {
+    let (records, open_error) = match open(path) {
+        Ok(reader) => (Some(binary_records(BufReader::new(reader))), None),
+        Err(err) => (None, Some(Err(err))),
+    };
+    open_error.into_iter().chain(records.into_iter().flatten())
+}
+
+//lazily decodes the records of reader, stopping after the first error
+fn binary_records<R: Read>(
+    mut reader: R,
+) -> impl Iterator<Item = Result<TaggedObjectId<u64, u64>, TraceError>> {
+    let mut failed = false;
+    std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let record = binary_record(&mut reader).transpose();
+        failed = matches!(record, Some(Err(_)));
+        record
+    })
+}
+
+//reads one binary record, None at a clean end of file
+fn binary_record(reader: &mut impl Read) -> Result<Option<TaggedObjectId<u64, u64>>, TraceError> {
+    let mut record = [0u8; 16];
+    let mut filled = 0;
+    while filled < record.len() {
+        match reader.read(&mut record[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    match filled {
+        0 => Ok(None),
+        16 => {
+            let (reference, address) = record.split_at(8);
+            Ok(Some(TaggedObjectId(
+                u64::from_le_bytes(reference.try_into().unwrap()),
+                u64::from_le_bytes(address.try_into().unwrap()),
+            )))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "truncated binary trace record",
+        )
+        .into()),
+    }
+}
+
+/// One access of a timed trace, `(timestamp, TaggedObjectId(reference, address))`.
+pub type TimedAccess = (u64, TaggedObjectId<u64, u64>);
+
+/// Reads a trace into `(timestamp, TaggedObjectId(reference, address))`
+/// pairs, with the timestamp taken from `config.timestamp_col`. Without a
+/// timestamp column the accesses are stamped 1, 2, 3, ..., the times at
+/// which a cache ticking once per access would see them.
+pub fn timed_trace_to_vec_with_config(
+    path: impl AsRef<Path>,
+    config: &TraceReaderConfig,
+) -> Result<Vec<TimedAccess>, TraceError> {
+    let mut reader = csv_reader(open(path)?, config.has_headers, config.delimiter);
+    let mut trace = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let timestamp = match config.timestamp_col {
+            Some(column) => parse_u64(&record, column, config.radix)?,
+            None => index as u64 + 1,
+        };
+        trace.push((
+            timestamp,
+            TaggedObjectId(
+                parse_u64(&record, config.ref_col, config.radix)?,
+                parse_u64(&record, config.addr_col, config.radix)?,
+            ),
+        ));
+    }
+    Ok(trace)
+}
+
+/// Reads a lease table into a map from reference to
+/// `(short_lease, long_lease, short_lease_prob)`. Each line holds
+/// `reference, short_lease, long_lease, short_lease_prob`, with the
+/// reference and both leases in hex and the probability a decimal float.
+/// Probabilities outside [0, 1], NaN included, are rejected with
+/// [`TraceError::InvalidProbability`].
+pub fn lease_to_map(
+    path: impl AsRef<Path>,
+) -> Result<HashMap<u64, (usize, usize, f64)>, TraceError> {
+    lease_to_map_with_radix(path, 16)
+}
+
+/// Like [`lease_to_map`], but the lease columns are decimal, as some lease
+/// generators write them. The reference column stays hex.
+pub fn lease_to_map_decimal(
+    path: impl AsRef<Path>,
+) -> Result<HashMap<u64, (usize, usize, f64)>, TraceError> {
+    lease_to_map_with_radix(path, 10)
+}
+
+/// Like [`lease_to_map`], but the lease columns are read in `lease_radix`.
+/// A lease that is not a number in that radix is a [`TraceError::ParseInt`]
+/// naming its line.
+pub fn lease_to_map_with_radix(
+    path: impl AsRef<Path>,
+    lease_radix: u32,
+) -> Result<HashMap<u64, (usize, usize, f64)>, TraceError> {
+    let mut reader = csv_reader(open(path)?, false, b',');
+    let mut lease_table = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let reference = parse_u64(&record, 0, 16)?;
+        let short_lease_prob = float(&record, 3)?;
+        if !(0.0..=1.0).contains(&short_lease_prob) {
+            return Err(TraceError::InvalidProbability {
+                line: line_of(&record),
+                reference,
+                probability: short_lease_prob,
+            });
+        }
+        lease_table.insert(
+            reference,
+            (
+                parse_usize(&record, 1, lease_radix)?,
+                parse_usize(&record, 2, lease_radix)?,
+                short_lease_prob,
+            ),
+        );
+    }
+    Ok(lease_table)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    pub(crate) fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    //writes contents to a fresh file under the system temp dir
+    pub(crate) fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("lease_cache_sim_{}_{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_trace_to_vec_u64() {
+        let trace = trace_to_vec_u64(fixture("trace.csv")).unwrap();
+        assert_eq!(trace.len(), 6);
+        assert_eq!(trace[0], TaggedObjectId(0x4005d0, 0x7ffe3a10));
+        assert_eq!(trace[5], TaggedObjectId(0x4005e8, 0x7ffe3a18));
+    }
+
+    #[test]
+    fn test_trace_to_vec_u64_gz() {
+        let plain = trace_to_vec_u64(fixture("trace.csv")).unwrap();
+        assert_eq!(trace_to_vec_u64(fixture("trace.csv.gz")).unwrap(), plain);
+        assert_eq!(trace_to_vec_u64_gz(fixture("trace.csv.gz")).unwrap(), plain);
+        //not actually compressed
+        assert!(matches!(
+            trace_to_vec_u64_gz(fixture("trace.csv")),
+            Err(TraceError::Csv(_)) | Err(TraceError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_trace_to_vec_with_config() {
+        let path = temp_file(
+            "decimal_trace.csv",
+            "reference,address\n100,4096\n101,4104\n100,4096\n",
+        );
+        let config = TraceReaderConfig {
+            ref_col: 0,
+            addr_col: 1,
+            timestamp_col: None,
+            radix: 10,
+            has_headers: true,
+            delimiter: b',',
+        };
+        let trace = trace_to_vec_with_config(&path, &config).unwrap();
+        assert_eq!(
+            trace,
+            vec![
+                TaggedObjectId(100, 4096),
+                TaggedObjectId(101, 4104),
+                TaggedObjectId(100, 4096)
+            ]
+        );
+        //the header row would not parse as data
+        assert!(trace_to_vec_with_config(
+            &path,
+            &TraceReaderConfig {
+                has_headers: false,
+                ..config
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_trace_to_vec_strings() {
+        let path = temp_file(
+            "symbol_trace.csv",
+            "main, R, buf\nloop, W, idx\nmain, R, buf\n",
+        );
+        let trace: Vec<TaggedObjectId<String, String>> =
+            trace_to_vec(&path, &TraceReaderConfig::default(), str::parse, str::parse).unwrap();
+        assert_eq!(trace.len(), 3);
+        assert_eq!(
+            trace[1],
+            TaggedObjectId("loop".to_string(), "idx".to_string())
+        );
+        assert_eq!(trace[0], trace[2]);
+        //a parser that fails still gets the line reported
+        let result = trace_to_vec(
+            &path,
+            &TraceReaderConfig::default(),
+            str::parse::<u64>,
+            str::parse::<String>,
+        );
+        assert!(matches!(
+            result,
+            Err(TraceError::ParseInt { line: Some(1), .. })
+        ));
+    }
+
+    #[test]
+    fn test_trace_reader_config_default() {
+        assert_eq!(
+            trace_to_vec_with_config(fixture("trace.csv"), &TraceReaderConfig::default()).unwrap(),
+            trace_to_vec_u64(fixture("trace.csv")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_trace_iter() {
+        assert_eq!(trace_iter(fixture("trace.csv")).count(), 6);
+        let streamed: Result<Vec<_>, _> = trace_iter(fixture("trace.csv.gz")).collect();
+        assert_eq!(
+            streamed.unwrap(),
+            trace_to_vec_u64(fixture("trace.csv")).unwrap()
+        );
+        let mut missing = trace_iter("/nonexistent/trace.csv");
+        assert!(matches!(missing.next(), Some(Err(TraceError::Io(_)))));
+        assert!(missing.next().is_none());
+    }
+
+    #[test]
+    fn test_timed_trace() {
+        let path = temp_file(
+            "timed_trace.csv",
+            "time,reference,address\n5,1,100\n5,2,200\n90,1,100\n",
+        );
+        let config = TraceReaderConfig {
+            ref_col: 1,
+            addr_col: 2,
+            timestamp_col: Some(0),
+            radix: 10,
+            has_headers: true,
+            delimiter: b',',
+        };
+        let trace = timed_trace_to_vec_with_config(&path, &config).unwrap();
+        assert_eq!(
+            trace,
+            vec![
+                (5, TaggedObjectId(1, 100)),
+                (5, TaggedObjectId(2, 200)),
+                (90, TaggedObjectId(1, 100))
+            ]
+        );
+        let untimed = TraceReaderConfig {
+            timestamp_col: None,
+            ..config
+        };
+        let timestamps: Vec<u64> = timed_trace_to_vec_with_config(&path, &untimed)
+            .unwrap()
+            .iter()
+            .map(|(timestamp, _)| *timestamp)
+            .collect();
+        assert_eq!(timestamps, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_lease_to_map() {
+        let lease_table = lease_to_map(fixture("lease_table.csv")).unwrap();
+        assert_eq!(lease_table.len(), 3);
+        assert_eq!(lease_table[&0x4005d0], (0x1a, 0x200, 0.75));
+        assert_eq!(lease_table[&0x4005e8], (0x2, 0x2, 1.0));
+    }
+
+    #[test]
+    fn test_lease_to_map_decimal() {
+        let path = temp_file(
+            "decimal_leases.csv",
+            "4005d0, 26, 512, 0.75\n4005e8, 10, 10, 1.0\n",
+        );
+        let lease_table = lease_to_map_decimal(&path).unwrap();
+        assert_eq!(lease_table[&0x4005d0], (26, 512, 0.75));
+        assert_eq!(lease_table[&0x4005e8], (10, 10, 1.0));
+        //read as hex, the same file silently means other leases
+        assert_eq!(lease_to_map(&path).unwrap()[&0x4005e8], (0x10, 0x10, 1.0));
+    }
+
+    #[test]
+    fn test_lease_to_map_rejects_digits_outside_radix() {
+        let path = temp_file("hex_leases.csv", "4005d0, 1a, 200, 0.75\n");
+        assert_eq!(
+            lease_to_map_with_radix(&path, 16).unwrap()[&0x4005d0],
+            (0x1a, 0x200, 0.75)
+        );
+        assert!(matches!(
+            lease_to_map_decimal(&path),
+            Err(TraceError::ParseInt { line: Some(1), .. })
+        ));
+        let path = temp_file(
+            "not_hex_leases.csv",
+            "4005d0, 2, 2, 1.0\n4005d8, 1g, 2, 1.0\n",
+        );
+        assert!(matches!(
+            lease_to_map(&path),
+            Err(TraceError::ParseInt { line: Some(2), .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let result = trace_to_vec_u64("/nonexistent/trace.csv");
+        assert!(matches!(result, Err(TraceError::Io(_))));
+    }
+
+    #[test]
+    fn test_malformed_trace_reports_line() {
+        let path = temp_file("malformed_trace.csv", "a, R, 10\nzz, R, 18\n");
+        let result = trace_to_vec_u64(&path);
+        assert!(matches!(
+            result,
+            Err(TraceError::ParseInt { line: Some(2), .. })
+        ));
+        let path = temp_file("short_trace.csv", "a, R\n");
+        let result = trace_to_vec_u64(&path);
+        assert!(matches!(
+            result,
+            Err(TraceError::MissingColumn {
+                line: Some(1),
+                column: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_lease_table() {
+        let path = temp_file("malformed_leases.csv", "a, 1, 2, 0.5\nb, 1, 2, high\n");
+        let result = lease_to_map(&path);
+        assert!(matches!(
+            result,
+            Err(TraceError::ParseFloat { line: Some(2), .. })
+        ));
+        assert!(result.unwrap_err().to_string().starts_with("line 2: "));
+    }
+
+    #[test]
+    fn test_lease_table_probability_out_of_range() {
+        let path = temp_file("bad_probability.csv", "a, 1, 2, 0.5\nb, 1, 2, 1.5\n");
+        let err = lease_to_map(&path).unwrap_err();
+        assert!(matches!(
+            err,
+            TraceError::InvalidProbability {
+                line: Some(2),
+                reference: 0xb,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "line 2: short lease probability 1.5 of reference b is not in [0, 1]"
+        );
+        let path = temp_file("nan_probability.csv", "a, 1, 2, NaN\n");
+        assert!(matches!(
+            lease_to_map(&path),
+            Err(TraceError::InvalidProbability { .. })
+        ));
+    }
+}
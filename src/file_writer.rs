@@ -0,0 +1,181 @@
+//! Writers for simulation results stored as CSV, the output side of
+//! [`file_reader`](crate::file_reader).
+//!
+//! A result file has a header row and then one row per access:
+//!
+//! ```text
+//! access_index,object,result,occupancy
+//! 0,7ffe3a10,miss,1
+//! ```
+//!
+//! `result` is `hit` or `miss` and `occupancy` is the number of objects in
+//! the cache right after the access.
+//!
+//! Traces can also be written in the binary format of
+//! [`read_binary_trace`](crate::file_reader::read_binary_trace), e.g. to
+//! convert a CSV trace once and replay it faster from then on.
+use abstract_cache::{AccessResult, CacheSim, ObjIdTraits};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::file_reader::TraceError;
+use crate::{Occupancy, SimStats, TaggedObjectId};
+
+/// Appends one row per access to a CSV result file.
+pub struct ResultWriter<W: Write> {
+    writer: csv::Writer<W>,
+    //index of the next access written
+    access_index: u64,
+}
+
+impl ResultWriter<File> {
+    /// Creates or truncates the file at `path` and writes the header row.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        Self::new(File::create(path)?)
+    }
+}
+
+impl<W: Write> ResultWriter<W> {
+    /// Wraps `writer` and writes the header row.
+    pub fn new(writer: W) -> Result<Self, TraceError> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(["access_index", "object", "result", "occupancy"])?;
+        Ok(ResultWriter {
+            writer,
+            access_index: 0,
+        })
+    }
+
+    /// Writes the row of the next access. Accesses are numbered from 0 in
+    /// the order they are written.
+    pub fn write_access(
+        &mut self,
+        obj_id: &impl Display,
+        result: &AccessResult,
+        occupancy: usize,
+    ) -> Result<(), TraceError> {
+        let result = match result {
+            AccessResult::Hit => "hit",
+            AccessResult::Miss => "miss",
+        };
+        self.writer.write_record([
+            self.access_index.to_string(),
+            obj_id.to_string(),
+            result.to_string(),
+            occupancy.to_string(),
+        ])?;
+        self.access_index += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), TraceError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes `trace` to the file at `path` in the binary trace format,
+/// creating or truncating it. Returns the number of accesses written.
+pub fn write_binary_trace<I>(path: impl AsRef<Path>, trace: I) -> Result<u64, TraceError>
+where
+    I: IntoIterator<Item = TaggedObjectId<u64, u64>>,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut written = 0;
+    for TaggedObjectId(reference, address) in trace {
+        writer.write_all(&reference.to_le_bytes())?;
+        writer.write_all(&address.to_le_bytes())?;
+        written += 1;
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Like [`run_trace`](crate::run_trace), but also writes every access to a
+/// result file at `path` with a [`ResultWriter`]. Objects are written with
+/// their `Display` form.
+pub fn run_trace_to_csv<Tag, Obj, C, I>(
+    cache: &mut C,
+    trace: I,
+    path: impl AsRef<Path>,
+) -> Result<SimStats, TraceError>
+where
+    Tag: ObjIdTraits,
+    Obj: ObjIdTraits,
+    C: CacheSim<TaggedObjectId<Tag, Obj>> + Occupancy,
+    I: IntoIterator<Item = TaggedObjectId<Tag, Obj>>,
+{
+    let mut writer = ResultWriter::create(path)?;
+    let mut hits = 0;
+    let mut misses = 0;
+    for access in trace {
+        let obj_id = access.1.clone();
+        let result = cache.cache_access(access);
+        match result {
+            AccessResult::Hit => hits += 1,
+            AccessResult::Miss => misses += 1,
+        }
+        writer.write_access(&obj_id, &result, cache.occupancy())?;
+    }
+    writer.flush()?;
+    Ok(SimStats::from_counts(hits, misses, cache.occupancy()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::file_reader::test::{fixture, temp_file};
+    use crate::file_reader::{read_binary_trace, trace_to_vec_u64};
+    use crate::LeaseCache;
+
+    #[test]
+    fn test_binary_trace_round_trip() {
+        let trace = trace_to_vec_u64(fixture("trace.csv")).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("lease_cache_sim_{}_trace.bin", std::process::id()));
+        assert_eq!(write_binary_trace(&path, trace.clone()).unwrap(), 6);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 6 * 16);
+        let read: Vec<_> = read_binary_trace(&path).collect::<Result<_, _>>().unwrap();
+        assert_eq!(read, trace);
+        //a record cut short is an error, after the complete ones
+        let path = temp_file("truncated_trace.bin", "0123456789abcdef012");
+        let read: Vec<_> = read_binary_trace(&path).collect();
+        assert_eq!(read.len(), 2);
+        assert!(
+            matches!(&read[1], Err(TraceError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_run_trace_to_csv() {
+        let path = std::env::temp_dir().join(format!(
+            "lease_cache_sim_{}_results.csv",
+            std::process::id()
+        ));
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let trace = vec![
+            TaggedObjectId(2, 10),
+            TaggedObjectId(2, 11),
+            TaggedObjectId(2, 10),
+        ];
+        let stats = run_trace_to_csv(&mut lease_cache, trace, &path).unwrap();
+        assert_eq!(stats.hits, 1);
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_path(&path)
+            .unwrap();
+        let rows: Vec<Vec<String>> = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+        let expected = [
+            ["access_index", "object", "result", "occupancy"],
+            ["0", "10", "miss", "1"],
+            ["1", "11", "miss", "2"],
+            ["2", "10", "hit", "2"],
+        ];
+        assert_eq!(rows, expected.map(|row| row.map(String::from)));
+    }
+}
@@ -0,0 +1,192 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+
+use crate::sim::Occupancy;
+use crate::{EvictReason, LeaseCache, TaggedObjectId, Time};
+
+/// Two-level inclusive lease cache. Every access goes to L1; an L1 miss
+/// falls through to L2, and the object is then cached in both levels. L1
+/// is kept inside L2: objects that L2 force-evicts or lets expire are also
+/// dropped from L1, under the same [`EvictReason`], and an L1 hit renews
+/// the L2 copy, bringing it back if L2 had let it go.
+///
+/// The lease tag of an access is the L1 lease. The L2 lease is derived from
+/// it by [`LeaseHierarchy::set_l2_lease`], which defaults to the same value.
+#[derive(Clone)]
+pub struct LeaseHierarchy<Obj: ObjIdTraits, T: Time = usize> {
+    l1: LeaseCache<Obj, T>,
+    l2: LeaseCache<Obj, T>,
+    l2_lease: fn(T) -> T,
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits> LeaseHierarchy<Obj, T> {
+    pub fn new(l1_capacity: usize, l2_capacity: usize) -> Self {
+        let mut l1 = LeaseCache::new();
+        l1.set_capacity(l1_capacity);
+        let mut l2 = LeaseCache::new();
+        l2.set_capacity(l2_capacity);
+        LeaseHierarchy {
+            l1,
+            l2,
+            l2_lease: |lease| lease,
+        }
+    }
+
+    /// Sets how the L2 lease of an access is computed from its L1 lease.
+    pub fn set_l2_lease(&mut self, l2_lease: fn(T) -> T) -> &mut Self {
+        self.l2_lease = l2_lease;
+        self
+    }
+
+    /// Sets the L1 capacity. [`CacheSim::set_capacity`] sets the L2 one.
+    pub fn set_l1_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.l1.set_capacity(cache_size);
+        self
+    }
+
+    /// L1, whose counters cover every access.
+    pub fn l1(&self) -> &LeaseCache<Obj, T> {
+        &self.l1
+    }
+
+    /// L2, whose counters only cover the accesses that missed in L1.
+    pub fn l2(&self) -> &LeaseCache<Obj, T> {
+        &self.l2
+    }
+
+    //drops obj_id from L1 because L2 no longer holds it
+    fn invalidate(&mut self, obj_id: &Obj, reason: EvictReason) {
+        if self.l1.is_stored(obj_id) {
+            self.l1.remove_entry(obj_id);
+            self.l1.notify_evict(obj_id, reason);
+        }
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits> CacheSim<TaggedObjectId<T, Obj>>
+    for LeaseHierarchy<Obj, T>
+{
+    /// Hit if either level hits.
+    fn cache_access(&mut self, access: TaggedObjectId<T, Obj>) -> AccessResult {
+        let TaggedObjectId(lease, obj_id) = access;
+        let l2_lease = self.l2.access_lease(&obj_id, (self.l2_lease)(lease));
+        let l1_result = self.l1.cache_access(TaggedObjectId(lease, obj_id.clone()));
+        //L2 ticks with L1 and, like update, looks the object up before the tick
+        let l2_expiration = self.l2.live_expiration(&obj_id);
+        self.l2.hold(&obj_id, l2_expiration);
+        let expired = self.l2.advance_time();
+        let result = match l1_result {
+            AccessResult::Hit => {
+                //refresh the L2 copy uncounted
+                self.l2.insert(obj_id.clone(), l2_lease);
+                AccessResult::Hit
+            }
+            AccessResult::Miss => self.l2.apply_access(&obj_id, l2_lease, l2_expiration),
+        };
+        let victims = self.l2.enforce_capacity();
+        for obj_id in &expired {
+            self.invalidate(obj_id, EvictReason::Expired);
+        }
+        for victim in &victims {
+            self.invalidate(victim, EvictReason::Capacity);
+        }
+        //an L2 lease of 0 leaves the object in L1 alone
+        if !self.l2.is_stored(&obj_id) {
+            self.invalidate(&obj_id, EvictReason::Expired);
+        }
+        result
+    }
+
+    /// Sets the L2 capacity; L1 keeps its own, see
+    /// [`LeaseHierarchy::set_l1_capacity`].
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.l2.set_capacity(cache_size);
+        self
+    }
+}
+
+/// Every object in the hierarchy is in L2, so this is L2's occupancy.
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits> Occupancy for LeaseHierarchy<Obj, T> {
+    fn occupancy(&self) -> usize {
+        self.l2.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_l1_miss_l2_hit() {
+        let mut hierarchy = LeaseHierarchy::<usize>::new(4, 16);
+        hierarchy.set_l2_lease(|lease| lease * 10);
+        hierarchy.cache_access(TaggedObjectId(1, 1));
+        for obj_id in 2..5 {
+            hierarchy.cache_access(TaggedObjectId(1, obj_id));
+        }
+        //1 has expired from L1 but is still leased in L2
+        assert!(!hierarchy.l1().contains(&1));
+        assert!(hierarchy.l2().contains(&1));
+        let result = hierarchy.cache_access(TaggedObjectId(1, 1));
+        assert!(matches!(result, AccessResult::Hit));
+        assert_eq!(hierarchy.l1().miss_count(), 5);
+        assert_eq!(hierarchy.l2().hit_count(), 1);
+        assert_eq!(hierarchy.l2().miss_count(), 4);
+        //and was promoted back into L1
+        assert!(hierarchy.l1().contains(&1));
+    }
+
+    #[test]
+    fn test_l2_victims_leave_l1() {
+        let mut hierarchy = LeaseHierarchy::<usize>::new(4, 1);
+        hierarchy.cache_access(TaggedObjectId(10, 1));
+        hierarchy.cache_access(TaggedObjectId(10, 2));
+        assert_eq!(hierarchy.l2().len(), 1);
+        assert_eq!(hierarchy.l1().len(), 1);
+        assert!(hierarchy.l1().contains(&2) == hierarchy.l2().contains(&2));
+    }
+
+    #[test]
+    fn test_l1_inside_l2() {
+        let mut hierarchy = LeaseHierarchy::<usize>::new(4, 6);
+        //L2 leases are shorter, so L2 lets objects go that L1 still holds
+        hierarchy.set_l2_lease(|lease| lease / 2);
+        for i in 0..2000 {
+            let obj_id = (i * 7) % 13 + i % 3;
+            hierarchy.cache_access(TaggedObjectId(i % 11 + 1, obj_id));
+            for (cached, _) in hierarchy.l1().iter() {
+                assert!(
+                    hierarchy.l2().contains(cached),
+                    "{} at access {}",
+                    cached,
+                    i
+                );
+            }
+        }
+        assert!(hierarchy.l1().expired_count() > 0);
+    }
+
+    #[test]
+    fn test_l1_hit_at_l2_expiry_tick() {
+        let mut hierarchy = LeaseHierarchy::<usize>::new(4, 16);
+        hierarchy.cache_access(TaggedObjectId(2, 1));
+        hierarchy.cache_access(TaggedObjectId(5, 2));
+        assert!(matches!(
+            hierarchy.cache_access(TaggedObjectId(2, 1)),
+            AccessResult::Hit
+        ));
+        assert!(hierarchy.l1().contains(&1) && hierarchy.l2().contains(&1));
+        assert_eq!(hierarchy.l2().expired_count(), 0);
+    }
+
+    #[test]
+    fn test_run_trace_through_hierarchy() {
+        let mut hierarchy = LeaseHierarchy::<usize>::new(2, 8);
+        hierarchy.set_l1_capacity(4);
+        let trace: Vec<_> = (0..100).map(|i| TaggedObjectId(6, i % 6)).collect();
+        let stats = crate::run_trace(&mut hierarchy, trace);
+        assert_eq!(stats.misses, 6);
+        assert_eq!(stats.final_occupancy, 6);
+    }
+}
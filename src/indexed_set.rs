@@ -0,0 +1,122 @@
+use rand::Rng;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::CacheHasher;
+
+/// A set that also keeps its members in a dense `Vec`, so a uniformly random
+/// member can be picked and removed in O(1).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Obj: serde::Serialize",
+        deserialize = "Obj: serde::Deserialize<'de>, S: CacheHasher"
+    ))
+)]
+pub(crate) struct IndexedSet<Obj: Hash + Eq + Clone, S = RandomState> {
+    members: Vec<Obj>,
+    //map from member to its index in members
+    positions: HashMap<Obj, usize, S>,
+}
+
+impl<Obj: Hash + Eq + Clone> IndexedSet<Obj, RandomState> {
+    #[cfg(test)]
+    pub(crate) fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<Obj: Hash + Eq + Clone, S: CacheHasher> IndexedSet<Obj, S> {
+    pub(crate) fn with_hasher(hasher: S) -> Self {
+        IndexedSet {
+            members: Vec::new(),
+            positions: HashMap::with_hasher(hasher),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub(crate) fn contains(&self, obj_id: &Obj) -> bool {
+        self.positions.contains_key(obj_id)
+    }
+
+    /// index of obj_id in the dense member list
+    pub(crate) fn position(&self, obj_id: &Obj) -> Option<usize> {
+        self.positions.get(obj_id).copied()
+    }
+
+    /// returns false if the object was already a member
+    pub(crate) fn insert(&mut self, obj_id: Obj) -> bool {
+        if self.positions.contains_key(&obj_id) {
+            return false;
+        }
+        self.positions.insert(obj_id.clone(), self.members.len());
+        self.members.push(obj_id);
+        true
+    }
+
+    /// returns false if the object was not a member
+    pub(crate) fn remove(&mut self, obj_id: &Obj) -> bool {
+        match self.positions.remove(obj_id) {
+            None => false,
+            Some(position) => {
+                self.swap_remove_at(position);
+                true
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.members.clear();
+        self.positions.clear();
+    }
+
+    /// a uniformly random member, left in the set
+    pub(crate) fn random<R: Rng>(&self, rng: &mut R) -> Option<&Obj> {
+        if self.members.is_empty() {
+            return None;
+        }
+        self.members.get(rng.gen_range(0..self.members.len()))
+    }
+
+    /// members in index order
+    pub(crate) fn iter(&self) -> std::slice::Iter<'_, Obj> {
+        self.members.iter()
+    }
+
+    //removes members[position] and patches up the index of the member moved into its place
+    fn swap_remove_at(&mut self, position: usize) -> Obj {
+        let obj_id = self.members.swap_remove(position);
+        if let Some(moved) = self.members.get(position) {
+            self.positions.insert(moved.clone(), position);
+        }
+        obj_id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_indexed_set_insert_remove() {
+        let mut set = IndexedSet::new();
+        assert!(set.insert(1));
+        assert!(set.insert(2));
+        assert!(set.insert(3));
+        assert!(!set.insert(2));
+        assert_eq!(set.len(), 3);
+        assert!(set.remove(&1));
+        assert!(!set.remove(&1));
+        assert!(!set.contains(&1));
+        assert!(set.contains(&2));
+        assert!(set.contains(&3));
+        assert_eq!(set.len(), 2);
+    }
+}
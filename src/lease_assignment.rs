@@ -0,0 +1,402 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::sim::Occupancy;
+use crate::{LeaseCache, TaggedObjectId};
+
+/// Map from reference to `(short_lease, long_lease, short_lease_prob)`, as
+/// read by [`lease_to_map`](crate::file_reader::lease_to_map).
+pub type LeaseTable<Tag> = HashMap<Tag, (usize, usize, f64)>;
+
+/// Turns one lease table entry `(short_lease, long_lease, short_lease_prob)`
+/// into a lease: the short lease with probability `short_lease_prob`, the
+/// long lease otherwise. A probability outside [0, 1] is clamped into it
+/// and a NaN one counts as 0, since tables built in memory are not checked
+/// the way [`lease_to_map`](crate::file_reader::lease_to_map) checks files.
+pub fn choose_lease(entry: (usize, usize, f64), rng: &mut impl Rng) -> usize {
+    let (short_lease, long_lease, _) = entry;
+    if rng.gen_bool(short_lease_prob(entry)) {
+        short_lease
+    } else {
+        long_lease
+    }
+}
+
+//the short_lease_prob of entry clamped into [0, 1], with NaN counting as 0
+fn short_lease_prob(entry: (usize, usize, f64)) -> f64 {
+    let (_, _, short_lease_prob) = entry;
+    if short_lease_prob.is_nan() {
+        0.0
+    } else {
+        short_lease_prob.clamp(0.0, 1.0)
+    }
+}
+
+/// How a [`LeaseAssignmentCache`] turns `short_lease_prob` into leases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AssignmentMode {
+    /// every access draws its lease with [`choose_lease`]
+    #[default]
+    Random,
+    /// no randomness: the accesses of each reference are counted and the
+    /// short lease goes to just enough of them to keep the fraction so far
+    /// at `short_lease_prob`, rounded down, e.g. every fourth access for 0.25
+    Deterministic,
+}
+
+/// The mean lease [`choose_lease`] draws from `entry`, i.e.
+/// `short_lease_prob * short_lease + (1 - short_lease_prob) * long_lease`,
+/// with the probability clamped the same way.
+pub fn expected_lease(entry: (usize, usize, f64)) -> f64 {
+    let (short_lease, long_lease, _) = entry;
+    let short_lease_prob = short_lease_prob(entry);
+    short_lease_prob * short_lease as f64 + (1.0 - short_lease_prob) * long_lease as f64
+}
+
+/// [`expected_lease`] of every reference in `lease_table`, to sanity-check a
+/// table, e.g. for swapped short and long columns, without replaying a trace.
+pub fn lease_table_preview<Tag: ObjIdTraits>(lease_table: &LeaseTable<Tag>) -> HashMap<Tag, f64> {
+    lease_table
+        .iter()
+        .map(|(reference, &entry)| (reference.clone(), expected_lease(entry)))
+        .collect()
+}
+
+/// References a lease table and a trace disagree on, as found by
+/// [`validate_against_trace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport<Tag: ObjIdTraits> {
+    /// references with a lease that the trace never accesses
+    pub unused_references: HashSet<Tag>,
+    /// references the trace accesses that would get the default lease
+    pub missing_references: HashSet<Tag>,
+}
+
+impl<Tag: ObjIdTraits> ValidationReport<Tag> {
+    /// True if the table covers exactly the references of the trace.
+    pub fn is_consistent(&self) -> bool {
+        self.unused_references.is_empty() && self.missing_references.is_empty()
+    }
+}
+
+/// Compares the references of `lease_table` with those accessed by
+/// `trace`, to catch a table generated for a different program before
+/// replaying with it.
+pub fn validate_against_trace<Tag, Obj, I>(
+    lease_table: &LeaseTable<Tag>,
+    trace: I,
+) -> ValidationReport<Tag>
+where
+    Tag: ObjIdTraits,
+    Obj: ObjIdTraits,
+    I: IntoIterator<Item = TaggedObjectId<Tag, Obj>>,
+{
+    let traced: HashSet<Tag> = trace
+        .into_iter()
+        .map(|TaggedObjectId(reference, _)| reference)
+        .collect();
+    ValidationReport {
+        unused_references: lease_table
+            .keys()
+            .filter(|reference| !traced.contains(*reference))
+            .cloned()
+            .collect(),
+        missing_references: traced
+            .into_iter()
+            .filter(|reference| !lease_table.contains_key(reference))
+            .collect(),
+    }
+}
+
+/// Lease tables that each apply during a window `[start, end)` of cache time,
+/// for programs whose phases want different leases. Windows are searched in
+/// the order they were added and the first one containing the time wins.
+#[derive(Debug, Clone)]
+pub struct PhasedLeaseTable<Tag: ObjIdTraits> {
+    phases: Vec<(Range<usize>, LeaseTable<Tag>)>,
+}
+
+impl<Tag: ObjIdTraits> PhasedLeaseTable<Tag> {
+    pub fn new() -> Self {
+        PhasedLeaseTable { phases: Vec::new() }
+    }
+
+    /// A single table that applies at all times.
+    pub fn single(lease_table: LeaseTable<Tag>) -> Self {
+        let mut phased = Self::new();
+        phased.add_phase(0, usize::MAX, lease_table);
+        phased
+    }
+
+    pub fn add_phase(
+        &mut self,
+        start: usize,
+        end: usize,
+        lease_table: LeaseTable<Tag>,
+    ) -> &mut Self {
+        self.phases.push((start..end, lease_table));
+        self
+    }
+
+    /// The table active at `time`, if any phase covers it.
+    pub fn table_at(&self, time: usize) -> Option<&LeaseTable<Tag>> {
+        self.phases
+            .iter()
+            .find(|(window, _)| window.contains(&time))
+            .map(|(_, lease_table)| lease_table)
+    }
+
+    /// The `(short_lease, long_lease, short_lease_prob)` entry for
+    /// `reference` at `time`.
+    pub fn lookup(&self, reference: &Tag, time: usize) -> Option<(usize, usize, f64)> {
+        self.table_at(time)?.get(reference).copied()
+    }
+}
+
+impl<Tag: ObjIdTraits> Default for PhasedLeaseTable<Tag> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps a [`LeaseCache`] and picks the lease of every access from a lease
+/// table keyed by reference, so a raw `(reference, object)` trace can be
+/// replayed directly.
+#[derive(Clone)]
+pub struct LeaseAssignmentCache<Tag: ObjIdTraits, Obj: ObjIdTraits> {
+    //map from ref to (short_lease, long_lease, short_lease_prob), per phase
+    pub(crate) lease_table: PhasedLeaseTable<Tag>,
+    //lease for references missing from the table
+    pub(crate) default_lease: usize,
+    pub(crate) cache: LeaseCache<Obj>,
+    pub(crate) rng: StdRng,
+    pub(crate) assignment_mode: AssignmentMode,
+    //map from ref to its number of accesses, for AssignmentMode::Deterministic
+    pub(crate) access_counts: HashMap<Tag, u64>,
+}
+
+impl<Tag: ObjIdTraits, Obj: ObjIdTraits> LeaseAssignmentCache<Tag, Obj> {
+    /// References missing from `lease_table` get a lease of 0, i.e. they are
+    /// never cached, until [`LeaseAssignmentCache::set_default_lease`] is called.
+    pub fn new(lease_table: LeaseTable<Tag>) -> Self {
+        Self::with_phases(PhasedLeaseTable::single(lease_table))
+    }
+
+    /// Like [`LeaseAssignmentCache::new`], but leases come from whichever
+    /// phase of `lease_table` covers the cache's current time. Times no
+    /// phase covers use the default lease.
+    pub fn with_phases(lease_table: PhasedLeaseTable<Tag>) -> Self {
+        LeaseAssignmentCache {
+            lease_table,
+            default_lease: 0,
+            cache: LeaseCache::new(),
+            rng: StdRng::from_entropy(),
+            assignment_mode: AssignmentMode::Random,
+            access_counts: HashMap::new(),
+        }
+    }
+
+    /// Like [`LeaseAssignmentCache::new`], but the short/long draws and the
+    /// eviction choices are all seeded from `seed`.
+    pub fn with_rng(lease_table: LeaseTable<Tag>, seed: u64) -> Self {
+        LeaseAssignmentCache {
+            lease_table: PhasedLeaseTable::single(lease_table),
+            default_lease: 0,
+            cache: LeaseCache::with_rng(seed),
+            rng: StdRng::seed_from_u64(seed),
+            assignment_mode: AssignmentMode::Random,
+            access_counts: HashMap::new(),
+        }
+    }
+
+    pub fn set_default_lease(&mut self, lease: usize) -> &mut Self {
+        self.default_lease = lease;
+        self
+    }
+
+    pub fn set_assignment_mode(&mut self, assignment_mode: AssignmentMode) -> &mut Self {
+        self.assignment_mode = assignment_mode;
+        self
+    }
+
+    /// The underlying cache, e.g. for reading its hit/miss counters.
+    pub fn cache(&self) -> &LeaseCache<Obj> {
+        &self.cache
+    }
+
+    /// Picks the lease for one access of `reference` as the
+    /// [`AssignmentMode`] says.
+    pub fn assign_lease(&mut self, reference: &Tag) -> usize {
+        let Some(entry) = self
+            .lease_table
+            .lookup(reference, self.cache.current_time())
+        else {
+            return self.default_lease;
+        };
+        match self.assignment_mode {
+            AssignmentMode::Random => choose_lease(entry, &mut self.rng),
+            AssignmentMode::Deterministic => {
+                let (short_lease, long_lease, _) = entry;
+                let short_lease_prob = short_lease_prob(entry);
+                let count = self.access_counts.entry(reference.clone()).or_default();
+                let shorts_before = (*count as f64 * short_lease_prob).floor();
+                *count += 1;
+                if (*count as f64 * short_lease_prob).floor() > shorts_before {
+                    short_lease
+                } else {
+                    long_lease
+                }
+            }
+        }
+    }
+}
+
+impl<Tag: ObjIdTraits, Obj: ObjIdTraits> CacheSim<TaggedObjectId<Tag, Obj>>
+    for LeaseAssignmentCache<Tag, Obj>
+{
+    /// input is a TaggedObjectId<Reference, ObjId>
+    fn cache_access(&mut self, access: TaggedObjectId<Tag, Obj>) -> AccessResult {
+        let TaggedObjectId(reference, obj_id) = access;
+        let lease = self.assign_lease(&reference);
+        self.cache.cache_access(TaggedObjectId(lease, obj_id))
+    }
+
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.cache.set_capacity(cache_size);
+        self
+    }
+}
+
+impl<Tag: ObjIdTraits, Obj: ObjIdTraits> Occupancy for LeaseAssignmentCache<Tag, Obj> {
+    fn occupancy(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_short_lease_prob_one() {
+        let lease_table = HashMap::from([(1u64, (2, 10, 1.0))]);
+        let mut lease_cache = LeaseAssignmentCache::<u64, usize>::with_rng(lease_table, 0);
+        lease_cache.set_capacity(100);
+        for obj_id in 0..50 {
+            lease_cache.cache_access(TaggedObjectId(1, obj_id));
+            assert_eq!(lease_cache.cache().time_until_eviction(&obj_id), Some(2));
+        }
+    }
+
+    #[test]
+    fn test_short_lease_prob_zero() {
+        let lease_table = HashMap::from([(1u64, (2, 10, 0.0))]);
+        let mut lease_cache = LeaseAssignmentCache::<u64, usize>::with_rng(lease_table, 0);
+        lease_cache.set_capacity(100);
+        for obj_id in 0..50 {
+            lease_cache.cache_access(TaggedObjectId(1, obj_id));
+            assert_eq!(lease_cache.cache().time_until_eviction(&obj_id), Some(10));
+        }
+    }
+
+    #[test]
+    fn test_missing_reference_uses_default_lease() {
+        let mut lease_cache = LeaseAssignmentCache::<u64, usize>::new(HashMap::new());
+        lease_cache.set_capacity(100);
+        lease_cache.cache_access(TaggedObjectId(7, 1));
+        assert!(!lease_cache.cache().contains(&1));
+        lease_cache.set_default_lease(3);
+        lease_cache.cache_access(TaggedObjectId(7, 1));
+        assert_eq!(lease_cache.cache().time_until_eviction(&1), Some(3));
+    }
+
+    #[test]
+    fn test_deterministic_assignment() {
+        let lease_table = HashMap::from([(1u64, (2, 10, 0.25)), (2, (3, 7, 0.5))]);
+        let mut lease_cache = LeaseAssignmentCache::<u64, usize>::new(lease_table);
+        lease_cache.set_assignment_mode(AssignmentMode::Deterministic);
+        let leases: Vec<usize> = (0..8).map(|_| lease_cache.assign_lease(&1)).collect();
+        assert_eq!(leases, vec![10, 10, 10, 2, 10, 10, 10, 2]);
+        //each reference keeps its own count
+        let leases: Vec<usize> = (0..4).map(|_| lease_cache.assign_lease(&2)).collect();
+        assert_eq!(leases, vec![7, 3, 7, 3]);
+    }
+
+    #[test]
+    fn test_choose_lease_fraction() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let draws = 100_000;
+        let short_draws = (0..draws)
+            .filter(|_| choose_lease((2, 10, 0.3), &mut rng) == 2)
+            .count();
+        let short_fraction = short_draws as f64 / draws as f64;
+        assert!((short_fraction - 0.3).abs() < 0.01, "{}", short_fraction);
+    }
+
+    #[test]
+    fn test_out_of_range_probability_is_clamped() {
+        let lease_table = HashMap::from([
+            (1u64, (2, 10, 1.5)),
+            (2, (2, 10, -0.5)),
+            (3, (2, 10, f64::NAN)),
+        ]);
+        let mut lease_cache = LeaseAssignmentCache::<u64, usize>::with_rng(lease_table, 0);
+        lease_cache.set_capacity(100);
+        for (reference, obj_id, lease) in [(1, 1, 2), (2, 2, 10), (3, 3, 10)] {
+            lease_cache.cache_access(TaggedObjectId(reference, obj_id));
+            assert_eq!(
+                lease_cache.cache().time_until_eviction(&obj_id),
+                Some(lease)
+            );
+        }
+    }
+
+    #[test]
+    fn test_expected_lease() {
+        assert_eq!(expected_lease((2, 10, 0.25)), 8.0);
+        assert_eq!(expected_lease((2, 10, 1.0)), 2.0);
+        assert_eq!(expected_lease((2, 10, 1.5)), 2.0);
+        assert_eq!(expected_lease((2, 10, -0.5)), 10.0);
+        assert_eq!(expected_lease((2, 10, f64::NAN)), 10.0);
+        let lease_table = HashMap::from([(1u64, (4, 8, 0.5)), (2, (3, 3, 0.9))]);
+        assert_eq!(
+            lease_table_preview(&lease_table),
+            HashMap::from([(1, 6.0), (2, 3.0)])
+        );
+    }
+
+    #[test]
+    fn test_validate_against_trace() {
+        let lease_table = HashMap::from([(1u64, (2, 2, 1.0)), (2, (4, 4, 1.0))]);
+        let trace = [1u64, 3, 1, 4].map(|reference| TaggedObjectId(reference, 0usize));
+        let report = validate_against_trace(&lease_table, trace.clone());
+        assert_eq!(report.unused_references, HashSet::from([2]));
+        assert_eq!(report.missing_references, HashSet::from([3, 4]));
+        assert!(!report.is_consistent());
+        let lease_table = HashMap::from([(1u64, (2, 2, 1.0)), (3, (1, 1, 1.0)), (4, (1, 1, 1.0))]);
+        assert!(validate_against_trace(&lease_table, trace).is_consistent());
+    }
+
+    #[test]
+    fn test_phased_lease_table() {
+        let mut phased = PhasedLeaseTable::new();
+        phased
+            .add_phase(0, 2, HashMap::from([(1u64, (2, 2, 1.0))]))
+            .add_phase(2, 4, HashMap::from([(1u64, (7, 7, 1.0))]));
+        let mut lease_cache = LeaseAssignmentCache::<u64, usize>::with_phases(phased);
+        lease_cache.set_capacity(100);
+        let mut leases = Vec::new();
+        for obj_id in 0..5 {
+            lease_cache.cache_access(TaggedObjectId(1, obj_id));
+            leases.push(lease_cache.cache().time_until_eviction(&obj_id));
+        }
+        //the last access falls outside both phases and gets the default 0
+        assert_eq!(leases, vec![Some(2), Some(2), Some(7), Some(7), None]);
+    }
+}
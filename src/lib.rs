@@ -1,15 +1,66 @@
-#![allow(dead_code)]
 #![allow(clippy::needless_return)]
+mod admission;
+mod builder;
+mod dueling;
+mod eviction;
+pub mod file_reader;
+pub mod file_writer;
+mod hierarchy;
+mod indexed_set;
+mod lease_assignment;
+mod lru;
+mod opt;
+mod reuse_distance;
+mod reuse_gap;
+mod set_associative;
+mod sim;
+mod time;
+mod uniform;
+mod working_set;
+
 use abstract_cache::AccessResult;
 use abstract_cache::CacheSim;
 use abstract_cache::ObjIdTraits;
+use rand::rngs::StdRng;
 use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::{Debug, Display};
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
+
+pub use crate::admission::{AdmissionPolicy, AdmissionPolicyClone, AlwaysAdmit};
+pub use crate::builder::LeaseCacheBuilder;
+pub use crate::dueling::{DuelingLeaseCache, LeasePolicy};
+pub use crate::eviction::{
+    EarliestExpiry, EvictLargestLease, EvictionPolicy, EvictionPolicyClone, InsertionOrder,
+    RandomEviction,
+};
+pub use crate::hierarchy::LeaseHierarchy;
+use crate::indexed_set::IndexedSet;
+pub use crate::lease_assignment::{
+    choose_lease, expected_lease, lease_table_preview, validate_against_trace, AssignmentMode,
+    LeaseAssignmentCache, LeaseTable, PhasedLeaseTable, ValidationReport,
+};
+pub use crate::lru::LruCache;
+pub use crate::opt::OptCache;
+pub use crate::reuse_distance::ReuseDistanceTracker;
+pub use crate::reuse_gap::ReuseGapTracker;
+pub use crate::set_associative::SetAssociativeLeaseCache;
+#[cfg(feature = "rayon")]
+pub use crate::sim::miss_ratio_curve_parallel;
+pub use crate::sim::{
+    miss_ratio_curve, run_timed_trace, run_trace, run_trace_by_reference, run_trace_with_warmup,
+    simulate_with_table, Occupancy, ReferenceStats, SimStats,
+};
+pub use crate::time::Time;
+pub use crate::uniform::UniformLeaseCache;
+pub use crate::working_set::WorkingSetTracker;
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TaggedObjectId<Tag: ObjIdTraits, Obj: ObjIdTraits>(pub Tag, pub Obj);
 impl<Tag: ObjIdTraits, Obj: ObjIdTraits> Display for TaggedObjectId<Tag, Obj> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,335 +70,2013 @@ impl<Tag: ObjIdTraits, Obj: ObjIdTraits> Display for TaggedObjectId<Tag, Obj> {
 
 impl<Tag: ObjIdTraits, Obj: ObjIdTraits> ObjIdTraits for TaggedObjectId<Tag, Obj> {}
 
+/// Size of the ring of expiration buckets the cache used to keep. Leases
+/// are no longer limited by it.
+#[deprecated(note = "expirations are kept in a BTreeMap and no longer wrap around")]
 pub const MAX_EXPIRING_VEC_SIZE: usize = 10000000;
-#[derive(Clone)]
-pub struct LeaseCache<Obj: ObjIdTraits> {
+
+/// Why an object left the cache, as passed to the
+/// [`LeaseCache::set_on_evict`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvictReason {
+    /// its lease ran out
+    Expired,
+    /// it was force-evicted to make room
+    Capacity,
+    /// it was removed explicitly
+    Manual,
+}
+
+/// What moves the clock of a [`LeaseCache`], chosen at construction with
+/// [`LeaseCache::with_clock_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClockMode {
+    /// every [`LeaseCache::update`], and so every `cache_access`, ticks the
+    /// clock once, so a lease of n covers the next n accesses
+    #[default]
+    PerAccess,
+    /// only `advance_time` and its variants move the clock, so a lease of n
+    /// covers n ticks of whatever time the caller advances by
+    Manual,
+}
+
+/// How many objects left a [`LeaseCache`] for each [`EvictReason`], as
+/// returned by [`LeaseCache::eviction_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvictionStats {
+    pub expired: u64,
+    pub capacity: u64,
+    pub manual: u64,
+}
+
+/// Returned by [`LeaseCache::try_insert`] when the object does not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheFull;
+
+impl Display for CacheFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cache is at capacity")
+    }
+}
+
+impl std::error::Error for CacheFull {}
+
+/// What one call of [`LeaseCache::advance_time_by`], or any of the calls
+/// built on it, did to the cache, as recorded in [`LeaseCache::tick_log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TickRecord<Obj, T> {
+    /// the clock after advancing
+    pub time: T,
+    /// objects that expired, soonest expiration first
+    pub evicted: Vec<Obj>,
+    /// objects left in the cache
+    pub occupancy: usize,
+}
+
+/// Bound alias for the hasher builder `S` of a [`LeaseCache`], implemented
+/// for every `BuildHasher` that is `Default` and `Clone`, such as
+/// `RandomState` or `BuildHasherDefault<H>`.
+pub trait CacheHasher: BuildHasher + Default + Clone {}
+
+impl<S: BuildHasher + Default + Clone> CacheHasher for S {}
+
+/// Called with every object that leaves a [`LeaseCache`].
+pub type EvictCallback<Obj> = Box<dyn FnMut(&Obj, EvictReason)>;
+
+/// `T` is the type of the clock, leases and expiration times. `S` builds
+/// the hasher of every internal map and set; the default SipHash is robust
+/// but slow on small integer keys, so a faster one such as FxHash can be
+/// plugged in for simulations.
+///
+/// With the `serde` feature the whole cache state can be checkpointed and
+/// restored. The eviction RNG is not part of that state: a restored cache
+/// draws its victims from a freshly seeded RNG.
+///
+/// Neither checkpoints nor clones carry over the eviction callback, and
+/// checkpoints do not carry over the eviction policy either.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Obj: serde::Serialize, T: serde::Serialize",
+        deserialize = "Obj: serde::Deserialize<'de>, T: serde::Deserialize<'de>, S: CacheHasher"
+    ))
+)]
+pub struct LeaseCache<Obj: ObjIdTraits, T: Time = usize, S = RandomState> {
     //map from ref to (short_lease, long_lease, short_lease_prob)
     // pub(crate) lease_table: HashMap<Tag, (usize, usize, f64)>,
-    pub(crate) expiring_vec: Vec<HashSet<Obj>>,
-    pub(crate) current_time: usize,
-    //map from ObjId to index in expiring_vec
-    pub(crate) content_map: HashMap<Obj, usize>,
+    //map from expiration time to the objects expiring then, holds no empty buckets
+    pub(crate) expiring_map: BTreeMap<T, HashSet<Obj, S>>,
+    pub(crate) current_time: T,
+    pub(crate) clock_mode: ClockMode,
+    //expirations are rounded up to multiples of this, ONE keeps them exact
+    pub(crate) granularity: T,
+    //map from ObjId to its expiration time in expiring_map
+    pub(crate) content_map: HashMap<Obj, T, S>,
     // pub(crate) cache_consumption: usize,
     pub(crate) cache_size: Option<usize>,
+    //source of randomness for picking eviction victims
+    #[cfg_attr(feature = "serde", serde(skip, default = "StdRng::from_entropy"))]
+    pub(crate) rng: StdRng,
+    //seed rng started from, None if it came from entropy
+    pub(crate) seed: Option<u64>,
+    //objects without a lease, kept out of content_map and expiring_map
+    pub(crate) pinned: HashSet<Obj, S>,
+    //every object in content_map or pinned, indexed so force_evict can pick one in O(1)
+    pub(crate) live_objects: IndexedSet<Obj, S>,
+    //map from ObjId to its size, for the cached objects whose size is not 1
+    pub(crate) sizes: HashMap<Obj, usize, S>,
+    //sum of the sizes of every object in live_objects
+    pub(crate) stored_size: usize,
+    //most objects ever cached at once since creation or the last clear
+    pub(crate) peak_occupancy: usize,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) eviction_stats: EvictionStats,
+    //map from lease left to the number of capacity or manual victims that had it
+    pub(crate) premature_evictions: BTreeMap<T, u64>,
+    //latest victim of force_evict or evict_earliest
+    pub(crate) last_evicted: Option<Obj>,
+    //accesses left that update the cache without counting as hits or misses
+    pub(crate) warmup_remaining: u64,
+    //whether accesses count towards hits and misses at all
+    pub(crate) track_stats: bool,
+    //map from ObjId to its number of accesses, None while not tracked
+    pub(crate) frequencies: Option<HashMap<Obj, u64, S>>,
+    //map from ObjId to the number of times it was force-evicted, None while not tracked
+    pub(crate) force_evictions: Option<HashMap<Obj, u64, S>>,
+    //ticks between occupancy samples, None while sampling is off
+    pub(crate) sample_interval: Option<T>,
+    pub(crate) next_sample: T,
+    pub(crate) occupancy_samples: Vec<(T, usize)>,
+    //buckets larger than this are recorded in hotspots when they expire
+    pub(crate) hotspot_threshold: Option<usize>,
+    pub(crate) hotspots: Vec<(T, usize)>,
+    //victims force-evicted per batch when over capacity, None to evict just the excess
+    pub(crate) eviction_batch: Option<usize>,
+    //(window, grace_lease) of the second chance mode, None while it is off
+    pub(crate) second_chance: Option<(T, T)>,
+    //map from leased ObjId to the time of its last access, kept while second_chance is on
+    pub(crate) last_access: HashMap<Obj, T, S>,
+    //map from ObjId to the lease cache_access uses when an access asks for 0
+    pub(crate) default_leases: HashMap<Obj, T, S>,
+    //one record per clock advance, None while not logging
+    pub(crate) tick_log: Option<Vec<TickRecord<Obj, T>>>,
+    //sum of len() over every tick advanced, and the number of those ticks
+    pub(crate) occupancy_area: u128,
+    pub(crate) occupancy_ticks: u128,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) on_evict: Option<EvictCallback<Obj>>,
+    //picks force_evict victims, None means uniformly at random from rng
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) eviction_policy: Option<Box<dyn EvictionPolicy<Obj, T, S>>>,
+    //decides which missed objects are cached, None admits all of them
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) admission_policy: Option<Box<dyn AdmissionPolicy<Obj, T>>>,
 }
-impl<Obj: ObjIdTraits> LeaseCache<Obj> {
+impl<Obj: ObjIdTraits, T: Time> LeaseCache<Obj, T, RandomState> {
     pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Creates a cache whose random eviction choices are driven by an RNG
+    /// seeded with `seed`, so simulations can be reproduced exactly.
+    pub fn with_rng(seed: u64) -> Self {
+        Self::with_rng_and_hasher(seed, RandomState::new())
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> LeaseCache<Obj, T, S> {
+    /// Creates a cache whose maps and sets hash objects with `hasher`, e.g. a
+    /// faster hasher than SipHash for integer object ids.
+    pub fn with_hasher(hasher: S) -> Self {
         LeaseCache {
-            expiring_vec: vec![HashSet::new(); MAX_EXPIRING_VEC_SIZE],
-            current_time: 0,
-            content_map: HashMap::new(),
+            expiring_map: BTreeMap::new(),
+            current_time: T::ZERO,
+            clock_mode: ClockMode::PerAccess,
+            granularity: T::ONE,
+            content_map: HashMap::with_hasher(hasher.clone()),
             // cache_consumption: 0,
             cache_size: None,
+            rng: StdRng::from_entropy(),
+            seed: None,
+            pinned: HashSet::with_hasher(hasher.clone()),
+            live_objects: IndexedSet::with_hasher(hasher.clone()),
+            sizes: HashMap::with_hasher(hasher.clone()),
+            stored_size: 0,
+            peak_occupancy: 0,
+            hits: 0,
+            misses: 0,
+            eviction_stats: EvictionStats::default(),
+            premature_evictions: BTreeMap::new(),
+            last_evicted: None,
+            warmup_remaining: 0,
+            track_stats: true,
+            frequencies: None,
+            force_evictions: None,
+            sample_interval: None,
+            next_sample: T::ZERO,
+            occupancy_samples: Vec::new(),
+            hotspot_threshold: None,
+            eviction_batch: None,
+            hotspots: Vec::new(),
+            second_chance: None,
+            last_access: HashMap::with_hasher(hasher.clone()),
+            default_leases: HashMap::with_hasher(hasher),
+            tick_log: None,
+            occupancy_area: 0,
+            occupancy_ticks: 0,
+            on_evict: None,
+            eviction_policy: None,
+            admission_policy: None,
         }
     }
 
-    pub fn insert(&mut self, obj_id: Obj, lease: usize) {
-        let absolute_index = (self.current_time + lease) % MAX_EXPIRING_VEC_SIZE;
-        self.expiring_vec[absolute_index].insert(obj_id.clone());
-        self.content_map.insert(obj_id, absolute_index);
+    /// [`LeaseCache::with_rng`] with a custom hasher.
+    pub fn with_rng_and_hasher(seed: u64, hasher: S) -> Self {
+        LeaseCache {
+            rng: StdRng::seed_from_u64(seed),
+            seed: Some(seed),
+            ..Self::with_hasher(hasher)
+        }
     }
 
-    pub fn update(&mut self, obj_id: &Obj, lease: usize) -> AccessResult {
-        let old_index = self.content_map.get(obj_id).cloned();
-        self.advance_time();
-        match old_index {
-            None => match lease {
-                0 => AccessResult::Miss,
-                _ => {
-                    self.insert(obj_id.clone(), lease);
-                    // self.cache_consumption += 1;
-                    // self.current_time = (self.current_time + 1) % MAX_EXPIRING_VEC_SIZE;
-                    AccessResult::Miss
-                }
-            },
-            Some(old_index) => {
-                self.expiring_vec[old_index]
-                    .remove(obj_id);
-                    // .then_some(())
-                    // .unwrap();
-                if lease != 0 {
-                    self.insert(obj_id.clone(), lease);
-                } else {
-                    self.content_map.remove(obj_id);
-                }
-                // self.current_time = (self.current_time + 1) % MAX_EXPIRING_VEC_SIZE;
-                AccessResult::Hit
-            }
+    /// Creates a cache whose clock is driven as `clock_mode` says.
+    pub fn with_clock_mode(clock_mode: ClockMode) -> Self {
+        LeaseCache {
+            clock_mode,
+            ..Self::default()
         }
-        
     }
 
-    pub fn contains(&self, obj_id: &Obj) -> bool {
-        self.content_map.contains_key(obj_id)
+    /// Creates a cache whose clock starts at `start_time` instead of 0, e.g.
+    /// to line it up with the timestamps of a trace. Leases are still
+    /// counted from the current time, so an object inserted right away with
+    /// lease n expires at `start_time + n`.
+    pub fn with_start_time(start_time: T) -> Self {
+        LeaseCache {
+            current_time: start_time,
+            ..Self::default()
+        }
     }
 
-    pub fn time_until_eviction(&self, obj_id: &Obj) -> Option<usize> {
-        let index = self.content_map.get(obj_id);
-        match index {
-            None => None,
-            Some(index) => {
-                let curr_index = self.current_time;
-                if *index > curr_index {
-                    return Some(*index - curr_index);
-                }
-                return Some(MAX_EXPIRING_VEC_SIZE - curr_index + *index);
+    pub fn clock_mode(&self) -> ClockMode {
+        self.clock_mode
+    }
+
+    /// Rounds every expiration scheduled from now on up to a multiple of
+    /// `granularity`, so objects whose leases end close together share one
+    /// bucket. This bounds the number of buckets to one per `granularity`
+    /// ticks of lease, at the cost of timing: an object may stay up to
+    /// `granularity - 1` ticks past the end of its lease, and so hit where an
+    /// exact cache would miss, but it never leaves early. A granularity of 1,
+    /// the default, keeps expirations exact. Objects already cached keep
+    /// their expiration until their lease changes.
+    pub fn set_expiration_granularity(&mut self, granularity: T) {
+        assert!(granularity > T::ZERO, "granularity must be positive");
+        self.granularity = granularity;
+    }
+
+    pub fn expiration_granularity(&self) -> T {
+        self.granularity
+    }
+
+    //rounds expiration up to the granularity, saturating at T::MAX
+    fn coarsen(&self, expiration: T) -> T {
+        match expiration % self.granularity {
+            remainder if remainder == T::ZERO => expiration,
+            remainder => expiration.saturating_add(self.granularity - remainder),
+        }
+    }
+
+    /// Schedules `obj_id` to expire `lease` ticks from now, replacing any
+    /// lease it already has. Expirations saturate at `T::MAX` rather than
+    /// wrapping into the past. A pinned object is unpinned.
+    ///
+    /// As with an access in [`LeaseCache::update`], a lease of 0 means the
+    /// object is not cached: it is dropped if present, which counts as
+    /// [`EvictReason::Expired`], and otherwise nothing happens. Pinned
+    /// objects never expire, so a lease of 0 leaves them pinned.
+    pub fn insert(&mut self, obj_id: Obj, lease: T) {
+        if lease == T::ZERO {
+            if self.content_map.contains_key(&obj_id) {
+                self.remove_entry(&obj_id);
+                self.notify_evict(&obj_id, EvictReason::Expired);
             }
+            return;
         }
+        self.pinned.remove(&obj_id);
+        let expiration = self.coarsen(self.current_time.saturating_add(lease));
+        match self.content_map.insert(obj_id.clone(), expiration) {
+            Some(old_expiration) => {
+                self.unschedule(&obj_id, old_expiration);
+            }
+            None => {
+                self.track(&obj_id);
+            }
+        }
+        self.expiring_map
+            .entry(expiration)
+            .or_default()
+            .insert(obj_id);
     }
 
-    pub fn get_cache_consumption(&self) -> usize {
-        self.content_map.len()
-        // self.cache_consumption
+    /// Inserts every `(obj_id, lease)` pair, as if by [`LeaseCache::insert`],
+    /// filling each expiration bucket in one go. If an object appears more
+    /// than once the last lease wins.
+    pub fn insert_many<I: IntoIterator<Item = (Obj, T)>>(&mut self, items: I) {
+        let mut buckets: HashMap<T, HashSet<Obj, S>> = HashMap::new();
+        for (obj_id, lease) in items {
+            if lease == T::ZERO {
+                if let Some(old_expiration) = self.content_map.get(&obj_id) {
+                    if let Some(bucket) = buckets.get_mut(old_expiration) {
+                        bucket.remove(&obj_id);
+                    }
+                }
+                if self.content_map.contains_key(&obj_id) {
+                    self.remove_entry(&obj_id);
+                    self.notify_evict(&obj_id, EvictReason::Expired);
+                }
+                continue;
+            }
+            self.pinned.remove(&obj_id);
+            let expiration = self.coarsen(self.current_time.saturating_add(lease));
+            match self.content_map.insert(obj_id.clone(), expiration) {
+                Some(old_expiration) => {
+                    //the old entry is either already scheduled or still in this batch
+                    self.unschedule(&obj_id, old_expiration);
+                    if let Some(bucket) = buckets.get_mut(&old_expiration) {
+                        bucket.remove(&obj_id);
+                    }
+                }
+                None => {
+                    self.track(&obj_id);
+                }
+            }
+            buckets.entry(expiration).or_default().insert(obj_id);
+        }
+        for (expiration, bucket) in buckets {
+            if !bucket.is_empty() {
+                self.expiring_map
+                    .entry(expiration)
+                    .or_default()
+                    .extend(bucket);
+            }
+        }
     }
 
-    pub fn remove(&mut self, obj_id: &Obj) {
-        let index = self.content_map.get(obj_id).unwrap();
-        self.expiring_vec[*index].remove(obj_id);
-        self.content_map.remove(obj_id);
-        // self.cache_consumption -= 1;
+    /// Like [`LeaseCache::insert`], but `obj_id` takes up `size` towards the
+    /// capacity instead of 1, e.g. its size in bytes. The size stays with the
+    /// object until it leaves the cache, plain `insert`s and accesses keep
+    /// it, and a later `insert_sized` replaces it.
+    pub fn insert_sized(&mut self, obj_id: Obj, lease: T, size: usize) {
+        if lease == T::ZERO {
+            self.insert(obj_id, lease);
+            return;
+        }
+        if self.live_objects.contains(&obj_id) {
+            self.stored_size = self.stored_size - self.size_of(&obj_id) + size;
+        }
+        match size {
+            1 => self.sizes.remove(&obj_id),
+            _ => self.sizes.insert(obj_id.clone(), size),
+        };
+        self.insert(obj_id, lease);
     }
 
-    pub fn advance_time(&mut self) -> HashSet<Obj> {
-        self.current_time = (self.current_time + 1) % MAX_EXPIRING_VEC_SIZE;
-        let expiring = self.expiring_vec[self.current_time].clone();
-        let expiring_copy = expiring.clone();
-        //decrement the cache consumption when expiring
-        // println!("expiring {:?}, cache consumption {:?}", expiring, self.cache_consumption);
-        // self.cache_consumption -= expiring.len();
-        //removing expiring from content map
-        expiring.iter().for_each(|obj_id| {
-            self.content_map.remove(obj_id);
-        });
-        self.expiring_vec[self.current_time].clear();
-        // self.curr_expiring_index = (self.curr_expiring_index + 1) % MAX_EXPIRING_VEC_SIZE;
+    /// The size `obj_id` takes up towards the capacity, see
+    /// [`LeaseCache::insert_sized`], or None if it is not in the cache.
+    pub fn size(&self, obj_id: &Obj) -> Option<usize> {
+        self.contains(obj_id).then(|| self.size_of(obj_id))
+    }
 
-        return expiring_copy;
+    //the size of a cached obj_id
+    fn size_of(&self, obj_id: &Obj) -> usize {
+        self.sizes.get(obj_id).copied().unwrap_or(1)
     }
 
-    pub fn remove_random_element<K, V>(map: &mut HashMap<K, V>) -> Option<(K, V)>
-    where
-        K: std::hash::Hash + Eq + Clone,
-        V: Clone,
-    {
-        if let Some((key, val)) = map.clone().iter().choose(&mut rand::thread_rng()) {
-            map.remove(key);
-            return Some((key.clone(), val.clone()));
+    /// Caches `obj_id` without a lease: it never expires, but can still be
+    /// force-evicted or removed. Lease operations such as
+    /// [`LeaseCache::renew`] skip pinned objects, while an access hits and
+    /// leaves them pinned. [`LeaseCache::insert`] gives the object a lease
+    /// again.
+    pub fn insert_pinned(&mut self, obj_id: Obj) {
+        if let Some(expiration) = self.content_map.remove(&obj_id) {
+            self.unschedule(&obj_id, expiration);
         }
-        None
+        self.track(&obj_id);
+        self.pinned.insert(obj_id);
     }
 
-    pub fn force_evict(&mut self) -> Obj {
-        // println!("content map before {:?}", self.content_map);
+    pub fn is_pinned(&self, obj_id: &Obj) -> bool {
+        self.pinned.contains(obj_id)
+    }
 
-        let (obj_id, absolute_index) =
-            LeaseCache::<Obj>::remove_random_element(&mut self.content_map).unwrap();
+    /// Number of pinned objects in the cache.
+    pub fn pinned_count(&self) -> usize {
+        self.pinned.len()
+    }
 
-        self.expiring_vec[absolute_index]
-            .remove(&obj_id.clone())
-            .then_some(())
-            .unwrap();
-        obj_id
+    //removes obj_id from the bucket at expiration, dropping the bucket once it is empty
+    fn unschedule(&mut self, obj_id: &Obj, expiration: T) {
+        if let Some(bucket) = self.expiring_map.get_mut(&expiration) {
+            bucket.remove(obj_id);
+            if bucket.is_empty() {
+                self.expiring_map.remove(&expiration);
+            }
+        }
     }
-}
 
-impl<Obj: ObjIdTraits> Default for LeaseCache<Obj> {
-    fn default() -> Self {
-        Self::new()
+    //adds obj_id to live_objects, telling the eviction policy if it is new
+    fn track(&mut self, obj_id: &Obj) {
+        if self.live_objects.insert(obj_id.clone()) {
+            self.stored_size += self.size_of(obj_id);
+            //an overshoot is only transient, enforce_capacity notes what is left
+            let over_capacity = self
+                .cache_size
+                .is_some_and(|cache_size| self.total_size() > cache_size);
+            if !over_capacity {
+                self.note_peak_occupancy();
+            }
+            if let Some(policy) = self.eviction_policy.as_mut() {
+                policy.on_insert(obj_id);
+            }
+        }
     }
-}
 
-impl<Obj: ObjIdTraits> CacheSim<TaggedObjectId<usize, Obj>> for LeaseCache<Obj> {
-    /// returns (total_access_count, miss_count)
-    /// input is an iterator of TaggedObjectId<Lease, ObjId>
-    fn cache_access(&mut self, access: TaggedObjectId<usize, Obj>) -> abstract_cache::AccessResult {
-        let TaggedObjectId(lease, obj_id) = access;
-        let cache_result = self.update(&obj_id, lease);
-        let cache_consumption = self.content_map.len();
-        if cache_consumption > self.cache_size.unwrap() {
-            self.force_evict();
-            // cache_consumption -= 1;
+    fn note_peak_occupancy(&mut self) {
+        self.peak_occupancy = self.peak_occupancy.max(self.len());
+    }
+
+    //drops obj_id from live_objects, telling the eviction policy if it was there
+    fn untrack(&mut self, obj_id: &Obj) {
+        if self.live_objects.remove(obj_id) {
+            self.stored_size -= self.size_of(obj_id);
+            self.sizes.remove(obj_id);
+            self.last_access.remove(obj_id);
+            if let Some(policy) = self.eviction_policy.as_mut() {
+                policy.on_remove(obj_id);
+            }
         }
-        return cache_result;
     }
 
-    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
-        self.cache_size = Some(cache_size);
-        self
+    //counts the eviction of obj_id and reports it to the eviction callback, if one is registered
+    fn notify_evict(&mut self, obj_id: &Obj, reason: EvictReason) {
+        match reason {
+            EvictReason::Expired => self.eviction_stats.expired += 1,
+            EvictReason::Capacity => self.eviction_stats.capacity += 1,
+            EvictReason::Manual => self.eviction_stats.manual += 1,
+        }
+        if let Some(on_evict) = self.on_evict.as_mut() {
+            on_evict(obj_id, reason);
+        }
     }
-}
 
-#[cfg(test)]
+    /// Registers `on_evict` to be called whenever an object leaves the
+    /// cache, replacing any previous callback. `clear` and its variants
+    /// drop their contents without calling it.
+    pub fn set_on_evict<F: FnMut(&Obj, EvictReason) + 'static>(&mut self, on_evict: F) {
+        self.on_evict = Some(Box::new(on_evict));
+    }
 
-mod test {
-    use super::*;
+    pub fn clear_on_evict(&mut self) {
+        self.on_evict = None;
+    }
 
-    #[test]
-    fn test_time_till_eviction() {
-        let mut lease_cache = LeaseCache::<usize>::new();
-        lease_cache.insert(1, 1);
-        lease_cache.insert(2, 2);
-        lease_cache.insert(3, 3);
-        // lease_cache.cache_consumption = 3;
-        assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
-        assert_eq!(lease_cache.time_until_eviction(&2), Some(2));
-        assert_eq!(lease_cache.time_until_eviction(&3), Some(3));
-        lease_cache.advance_time();
+    /// Makes `policy` pick the victims of [`LeaseCache::force_evict`], and so
+    /// of the capacity check in `cache_access`, replacing any previous
+    /// policy. Objects already cached are reported to its
+    /// [`EvictionPolicy::on_insert`] first, in the cache's internal order.
+    pub fn set_eviction_policy<P: EvictionPolicy<Obj, T, S> + 'static>(&mut self, mut policy: P) {
+        for obj_id in self.live_objects.iter() {
+            policy.on_insert(obj_id);
+        }
+        self.eviction_policy = Some(Box::new(policy));
+    }
 
-        println!("{:?}", lease_cache.time_until_eviction(&1));
-        assert_eq!(lease_cache.time_until_eviction(&2), Some(1));
-        assert_eq!(lease_cache.time_until_eviction(&3), Some(2));
+    /// Goes back to evicting uniformly at random.
+    pub fn clear_eviction_policy(&mut self) {
+        self.eviction_policy = None;
     }
 
-    #[test]
-    fn test_lease_zero() {
-        let mut lease_cache = LeaseCache::<usize>::new();
-        lease_cache.update(&1, 4);
-        lease_cache.update(&2, 0);
-        lease_cache.update(&1, 0);
-        assert_eq!(lease_cache.time_until_eviction(&1), None);
-        assert_eq!(lease_cache.time_until_eviction(&2), None);
-        assert!(!lease_cache.content_map.contains_key(&2));
-        assert!(!lease_cache.content_map.contains_key(&1));
-        assert!(lease_cache.get_cache_consumption() == 0);
+    /// Makes `policy` decide whether each object that misses in
+    /// [`LeaseCache::update`] and its variants is cached, replacing any
+    /// previous policy. Direct inserts bypass it.
+    pub fn set_admission_policy<P: AdmissionPolicy<Obj, T> + 'static>(&mut self, policy: P) {
+        self.admission_policy = Some(Box::new(policy));
     }
 
-    #[test]
-    fn test_lease_cache_new() {
-        let lease_cache = LeaseCache::<usize>::new();
-        assert_eq!(lease_cache.expiring_vec.len(), self::MAX_EXPIRING_VEC_SIZE);
-        assert_eq!(lease_cache.current_time, 0);
-        assert_eq!(lease_cache.content_map.len(), 0);
+    /// Goes back to admitting every missed object.
+    pub fn clear_admission_policy(&mut self) {
+        self.admission_policy = None;
     }
 
-    #[test]
-    fn test_lease_cache_insert() {
-        let mut lease_cache = LeaseCache::<usize>::new();
-        lease_cache.insert(1, 1);
-        lease_cache.insert(2, 2);
-        lease_cache.insert(3, 3);
-        assert!(lease_cache.content_map.contains_key(&1));
-        assert!(lease_cache.content_map.contains_key(&2));
-        assert!(lease_cache.content_map.contains_key(&3));
-        let mut abs_index = lease_cache.content_map.get(&1).unwrap();
-        assert_eq!(lease_cache.expiring_vec[*abs_index].contains(&1), true);
-        abs_index = lease_cache.content_map.get(&2).unwrap();
-        assert_eq!(lease_cache.expiring_vec[*abs_index].contains(&2), true);
-        abs_index = lease_cache.content_map.get(&3).unwrap();
-        assert_eq!(lease_cache.expiring_vec[*abs_index].contains(&3), true);
+    /// Looks `obj_id` up, advances the clock one tick and then renews the
+    /// object with `lease`, as [`LeaseCache::update_no_tick`] would. The
+    /// lookup happens before the tick, so an object whose lease ends at
+    /// exactly that tick still hits.
+    ///
+    /// In [`ClockMode::Manual`] the tick is skipped and this is the same as
+    /// `update_no_tick`.
+    pub fn update(&mut self, obj_id: &Obj, lease: T) -> AccessResult {
+        let old_expiration = self.live_expiration(obj_id);
+        self.hold(obj_id, old_expiration);
+        if self.clock_mode == ClockMode::PerAccess {
+            self.advance_time();
+        }
+        self.apply_access(obj_id, lease, old_expiration)
     }
 
-    #[test]
-    fn test_lease_cache_update() {
-        let mut lease_cache = LeaseCache::<usize>::new();
-        // Update the lease cache with obj_id 1 and index 1
-        lease_cache.update(&1, 1);
-        // Get the absolute index of obj_id 1 and release the immutable borrow
-        let abs_index: usize = *lease_cache.content_map.get(&1).unwrap();
-        assert!(lease_cache.expiring_vec[abs_index].contains(&1));
-        // Update the lease cache with obj_id 1 and new index 4
-        lease_cache.update(&1, 4);
-        assert!(lease_cache.content_map.keys().len() == 1);
-        // Get the old absolute index and assert it no longer contains obj_id 1
-        // let abs_index_old = abs_index; // Reuse the old index
-        println!(
-            "results {}",
-            lease_cache.expiring_vec[abs_index].contains(&1)
-        );
-        assert!(!lease_cache.expiring_vec[abs_index].contains(&1));
-        assert!(lease_cache.content_map.keys().len() == 1);
-        // Get the new absolute index and assert it contains obj_id 1
-        let abs_index_new = *lease_cache.content_map.get(&1).unwrap();
-        assert!(lease_cache.expiring_vec[abs_index_new].contains(&1));
+    //takes a hit object out of its bucket, so the tick before it is renewed
+    //does not drain it as expired; apply_access schedules it again
+    fn hold(&mut self, obj_id: &Obj, old_expiration: Option<T>) {
+        if let Some(expiration) = old_expiration {
+            self.unschedule(obj_id, expiration);
+        }
     }
 
-    #[test]
-    fn test_lease_cache_dump_expiring() {
-        let mut lease_cache = LeaseCache::<usize>::new();
-        //test to make sure expiring objects are dumped correctly,
-        //this means that each time we dump we see the objects that the correct
-        //objects are expiring and the expiring index is incremented by one
-        lease_cache.insert(1, 1);
-        lease_cache.insert(2, 2);
-        lease_cache.insert(3, 3);
-        // lease_cache.cache_consumption = 3;
-        let mut expiring = lease_cache.advance_time();
-        let mut expected = HashSet::new();
-        expected.insert(1);
-        assert_eq!(expiring, expected);
-        expiring = lease_cache.advance_time();
-        expected.insert(2);
-        expected.remove(&1);
-        assert_eq!(expiring, expected);
-        assert!(!lease_cache.content_map.contains_key(&1));
-        expiring = lease_cache.advance_time();
-        expected.insert(3);
-        expected.remove(&2);
-        assert_eq!(expiring, expected);
-        assert!(!lease_cache.content_map.contains_key(&2));
-        // assert_eq!(lease_cache.cache_consumption, 0);
-        assert!(lease_cache.content_map.is_empty())
+    /// Records an access to `obj_id` at the current time without moving the
+    /// clock, for callers that advance time themselves: a hit if the object
+    /// is in the cache, after which it is renewed with `lease`. A lease of 0
+    /// on a hit drops the object, which counts as [`EvictReason::Expired`];
+    /// on a miss it leaves the object uncached.
+    pub fn update_no_tick(&mut self, obj_id: &Obj, lease: T) -> AccessResult {
+        let old_expiration = self.live_expiration(obj_id);
+        self.apply_access(obj_id, lease, old_expiration)
+    }
 
-        //TODO: test that expiring index is incremented correctly at the boundry
+    /// Records an access to `obj_id` happening at the absolute time `time`:
+    /// the clock is moved there with [`LeaseCache::advance_time_to`] and the
+    /// object renewed with `lease`. Like [`LeaseCache::update`], an object
+    /// whose lease ends at exactly `time` still hits, so stamping accesses
+    /// 1, 2, 3, ... behaves the same as calling `update` for each. A `time`
+    /// at or before the current time is treated as the current time.
+    pub fn update_at(&mut self, obj_id: &Obj, lease: T, time: T) -> AccessResult {
+        let old_expiration = self
+            .live_expiration(obj_id)
+            .filter(|&expiration| expiration >= time);
+        self.hold(obj_id, old_expiration);
+        self.advance_time_to(time);
+        self.apply_access(obj_id, lease, old_expiration)
     }
 
-    fn test_lease_cache_force_evict() {
-        let epsilon = 0.1;
-        let num_iters = 100;
-        //we want to test that each object in the cache has an equal chance of being evicted
-        let mut num_obj1_evicted = 0;
-        let mut num_obj2_evicted = 0;
-        let mut num_obj3_evicted = 0;
-        for _ in 0..num_iters {
-            let mut lease_cache = LeaseCache::<usize>::new();
-            lease_cache.insert(1, 100000);
-            lease_cache.insert(2, 100000);
-            lease_cache.insert(3, 9);
-            let evicted_obj = lease_cache.force_evict();
-            match evicted_obj {
-                1 => num_obj1_evicted += 1,
-                2 => num_obj2_evicted += 1,
-                3 => num_obj3_evicted += 1,
-                _ => panic!("Invalid object evicted"),
+    //old_expiration is what the lookup for this access found
+    fn apply_access(&mut self, obj_id: &Obj, lease: T, old_expiration: Option<T>) -> AccessResult {
+        let cache_result = match old_expiration {
+            _ if self.pinned.contains(obj_id) => AccessResult::Hit,
+            None => {
+                //an overdue entry is drained first so it is reported as expired
+                if self.content_map.contains_key(obj_id) {
+                    self.advance_time_by(T::ZERO);
+                }
+                let admitted = match self.admission_policy.as_mut() {
+                    Some(policy) => policy.admit(obj_id, lease),
+                    None => true,
+                };
+                if admitted {
+                    self.insert(obj_id.clone(), lease);
+                }
+                // self.cache_consumption += 1;
+                AccessResult::Miss
             }
-            // if i % 10 == 1 {
-            //     println!("{} ", i)
-            // }
+            Some(_) => {
+                //a lease of 0 drops the object here, see insert
+                self.insert(obj_id.clone(), lease);
+                AccessResult::Hit
+            }
+        };
+        if let Some(frequencies) = self.frequencies.as_mut() {
+            *frequencies.entry(obj_id.clone()).or_default() += 1;
         }
-        //check that each object was evicted is within a small epsilon
-        let check_obj1 =
-            ((num_obj1_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
-        let check_obj2 =
-            ((num_obj2_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
-        let check_obj3 =
-            ((num_obj3_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
-        println!(
-            "eviction count: {} {} {}",
-            num_obj1_evicted, num_obj2_evicted, num_obj3_evicted
-        );
-        println!(
-            "eviction ratio: {} {} {}",
-            num_obj1_evicted as f64 / num_iters as f64,
-            num_obj2_evicted as f64 / num_iters as f64,
-            num_obj3_evicted as f64 / num_iters as f64
-        );
-        assert!(check_obj1 && check_obj2 && check_obj3);
+        if self.second_chance.is_some() && self.content_map.contains_key(obj_id) {
+            self.last_access.insert(obj_id.clone(), self.current_time);
+        }
+        if !self.track_stats {
+            return cache_result;
+        }
+        if self.warmup_remaining > 0 {
+            self.warmup_remaining -= 1;
+        } else {
+            match cache_result {
+                AccessResult::Hit => self.hits += 1,
+                AccessResult::Miss => self.misses += 1,
+            }
+        }
+        cache_result
     }
 
-    #[test]
-    fn test_lease_cache_force_evict_string() {
-        let epsilon = 0.2;
-        let num_iters = 100;
-        //we want to test that each object in the cache has an equal chance of being evicted
-        let mut num_obj1_evicted = 0;
-        let mut num_obj2_evicted = 0;
-        let mut num_obj3_evicted = 0;
-        let obj_1 = "x1";
-        let obj_2 = "x2";
-        let obj_3 = "x3";
-        let mut lease_cache = LeaseCache::<String>::new();
+    /// Adds `extra` ticks to whatever lease `obj_id` has left. Returns false,
+    /// changing nothing, if the object is not in the cache or its lease has
+    /// already run out.
+    pub fn extend_lease(&mut self, obj_id: &Obj, extra: T) -> bool {
+        let Some(old_expiration) = self.live_expiration(obj_id) else {
+            return false;
+        };
+        self.reschedule(obj_id, self.coarsen(old_expiration.saturating_add(extra)));
+        true
+    }
 
-        for _i in 0..num_iters {
+    /// Sets the remaining lease of `obj_id` to exactly `lease` ticks from
+    /// now. Unlike [`LeaseCache::update`] the clock does not move. Returns
+    /// false, changing nothing, if the object is not in the cache or its
+    /// lease has already run out. A lease of 0 drops the object, as in
+    /// [`LeaseCache::insert`].
+    pub fn renew(&mut self, obj_id: &Obj, lease: T) -> bool {
+        if self.live_expiration(obj_id).is_none() {
+            return false;
+        }
+        if lease == T::ZERO {
+            self.insert(obj_id.clone(), lease);
+            return true;
+        }
+        self.reschedule(
+            obj_id,
+            self.coarsen(self.current_time.saturating_add(lease)),
+        );
+        true
+    }
+
+    //moves obj_id, which must be in the cache, to the bucket at expiration
+    fn reschedule(&mut self, obj_id: &Obj, expiration: T) {
+        let old_expiration = self.content_map.insert(obj_id.clone(), expiration).unwrap();
+        self.unschedule(obj_id, old_expiration);
+        self.expiring_map
+            .entry(expiration)
+            .or_default()
+            .insert(obj_id.clone());
+    }
+
+    /// Same as [`LeaseCache::is_live`]: an object whose lease has run out is
+    /// not reported even if its expiration bucket is still waiting to be
+    /// drained by the next advance of the clock.
+    pub fn contains(&self, obj_id: &Obj) -> bool {
+        self.is_live(obj_id)
+    }
+
+    //whether obj_id is in content_map or pinned, overdue or not
+    fn is_stored(&self, obj_id: &Obj) -> bool {
+        self.content_map.contains_key(obj_id) || self.pinned.contains(obj_id)
+    }
+
+    //the expiration of obj_id if it is leased and live
+    fn live_expiration(&self, obj_id: &Obj) -> Option<T> {
+        self.content_map
+            .get(obj_id)
+            .copied()
+            .filter(|&expiration| expiration > self.current_time)
+    }
+
+    //objects in buckets the clock has reached but that were not drained yet
+    fn overdue_len(&self) -> usize {
+        self.expiring_map
+            .range(..=self.current_time)
+            .map(|(_, bucket)| bucket.len())
+            .sum()
+    }
+
+    /// Whether `obj_id` is cached and its lease runs past the current time,
+    /// even if its expiration bucket has not been drained yet. Pinned
+    /// objects are always live. Nothing is drained or otherwise changed.
+    pub fn is_live(&self, obj_id: &Obj) -> bool {
+        match self.content_map.get(obj_id) {
+            Some(&expiration) => expiration > self.current_time,
+            None => self.pinned.contains(obj_id),
+        }
+    }
+
+    /// Like [`LeaseCache::is_live`], but first drains every bucket the clock
+    /// has already reached, without moving it, so the memory of overdue
+    /// objects is reclaimed. They are reported as [`EvictReason::Expired`].
+    pub fn contains_unexpired(&mut self, obj_id: &Obj) -> bool {
+        self.advance_time_by(T::ZERO);
+        self.contains(obj_id)
+    }
+
+    /// None if `obj_id` is not in the cache or is pinned, i.e. never expires.
+    pub fn time_until_eviction(&self, obj_id: &Obj) -> Option<T> {
+        self.live_expiration(obj_id)
+            .map(|expiration| expiration - self.current_time)
+    }
+
+    /// The absolute time at which `obj_id` expires; see
+    /// [`LeaseCache::current_time`] for the clock it is measured against.
+    pub fn get_expiration(&self, obj_id: &Obj) -> Option<T> {
+        self.live_expiration(obj_id)
+    }
+
+    pub fn current_time(&self) -> T {
+        self.current_time
+    }
+
+    /// The earliest expiration time of any object in the cache.
+    pub fn next_expiration_time(&self) -> Option<T> {
+        self.expiring_map.keys().next().copied()
+    }
+
+    /// The earliest expiration time still in the cache together with the
+    /// objects expiring then, without advancing the clock.
+    pub fn peek_next_expiring(&self) -> Option<(T, &HashSet<Obj, S>)> {
+        self.expiring_map
+            .first_key_value()
+            .map(|(expiration, bucket)| (*expiration, bucket))
+    }
+
+    /// A copy of the expiration schedule: every expiration time still ahead,
+    /// with the objects expiring then. Pinned objects are not scheduled.
+    pub fn expiration_schedule(&self) -> BTreeMap<T, Vec<Obj>> {
+        self.expiring_map
+            .iter()
+            .map(|(expiration, bucket)| (*expiration, bucket.iter().cloned().collect()))
+            .collect()
+    }
+
+    /// Every leased object in the cache with its time until eviction, in no
+    /// particular order. Pinned objects are left out.
+    pub fn iter(&self) -> impl Iterator<Item = (&Obj, T)> {
+        self.content_map
+            .iter()
+            .filter(|(_, &expiration)| expiration > self.current_time)
+            .map(|(obj_id, &expiration)| (obj_id, expiration - self.current_time))
+    }
+
+    /// Every object in the cache, pinned ones included, in no particular
+    /// order.
+    pub fn objects(&self) -> impl Iterator<Item = &Obj> {
+        self.iter().map(|(obj_id, _)| obj_id).chain(&self.pinned)
+    }
+
+    /// Counts the leased objects in the cache by remaining lease, i.e.
+    /// expiration minus `current_time`.
+    pub fn lease_histogram(&self) -> BTreeMap<T, usize> {
+        let mut histogram = BTreeMap::new();
+        for (_, lease) in self.iter() {
+            *histogram.entry(lease).or_default() += 1;
+        }
+        histogram
+    }
+
+    /// Objects whose remaining lease is at most `horizon`, i.e. that expire in
+    /// `(current_time, current_time + horizon]`, soonest first. Overdue
+    /// objects and pinned ones are left out.
+    pub fn expiring_within(&self, horizon: T) -> Vec<&Obj> {
+        use std::ops::Bound::{Excluded, Included};
+        let end = self.current_time.saturating_add(horizon);
+        self.expiring_map
+            .range((Excluded(self.current_time), Included(end)))
+            .flat_map(|(_, bucket)| bucket)
+            .collect()
+    }
+
+    /// The objects whose lease ends exactly at the absolute time `time`, or
+    /// None if there are none. Buckets that are already due are not
+    /// reported, as their objects no longer count as cached.
+    pub fn objects_expiring_at(&self, time: T) -> Option<&HashSet<Obj, S>> {
+        if time <= self.current_time {
+            return None;
+        }
+        self.expiring_map.get(&time)
+    }
+
+    /// Number of distinct expiration times among the objects in the cache.
+    pub fn expiration_bucket_count(&self) -> usize {
+        self.expiring_map.len()
+    }
+
+    /// Number of objects sharing the most common expiration time, 0 for an
+    /// empty cache.
+    pub fn max_bucket_size(&self) -> usize {
+        self.expiring_map
+            .values()
+            .map(HashSet::len)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Rough heap usage of the cache in bytes, from the number of objects and
+    /// expiration buckets plus whatever the optional trackers and logs hold.
+    /// Hash table slack and any heap data owned by `Obj` itself (e.g.
+    /// `String` contents) are not counted.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        use std::mem::size_of;
+        //one control byte per hash table slot
+        let hash_entry = |payload: usize| payload + 1;
+        let per_object =
+            //content_map
+            hash_entry(size_of::<Obj>() + size_of::<T>())
+            //its expiration bucket
+            + hash_entry(size_of::<Obj>())
+            //live_objects: members and positions
+            + size_of::<Obj>()
+            + hash_entry(size_of::<Obj>() + size_of::<usize>());
+        let per_pinned = hash_entry(size_of::<Obj>())
+            + size_of::<Obj>()
+            + hash_entry(size_of::<Obj>() + size_of::<usize>());
+        let per_bucket = size_of::<T>() + size_of::<HashSet<Obj, S>>();
+        let per_count = hash_entry(size_of::<Obj>() + size_of::<u64>());
+        let per_time = hash_entry(size_of::<Obj>() + size_of::<T>());
+        let counted =
+            |counts: &Option<HashMap<Obj, u64, S>>| counts.as_ref().map_or(0, HashMap::len);
+        let tick_log = self.tick_log.as_ref().map_or(0, |tick_log| {
+            tick_log
+                .iter()
+                .map(|record| {
+                    size_of::<TickRecord<Obj, T>>() + record.evicted.len() * size_of::<Obj>()
+                })
+                .sum()
+        });
+        size_of::<Self>()
+            + self.content_map.len() * per_object
+            + self.pinned.len() * per_pinned
+            + self.expiring_map.len() * per_bucket
+            + self.occupancy_samples.len() * size_of::<(T, usize)>()
+            + self.hotspots.len() * size_of::<(T, usize)>()
+            + self.sizes.len() * hash_entry(size_of::<Obj>() + size_of::<usize>())
+            + (self.last_access.len() + self.default_leases.len()) * per_time
+            + (counted(&self.frequencies) + counted(&self.force_evictions)) * per_count
+            //premature_evictions is a BTreeMap, count its entries without node overhead
+            + self.premature_evictions.len() * size_of::<(T, u64)>()
+            + tick_log
+    }
+
+    /// Number of objects currently in the cache, pinned ones included.
+    /// Objects whose lease has run out are not counted, see
+    /// [`LeaseCache::contains`].
+    pub fn len(&self) -> usize {
+        self.stored_len() - self.overdue_len()
+    }
+
+    /// The most objects the cache has held at once since it was created or
+    /// last cleared, i.e. the smallest capacity that would have avoided every
+    /// capacity eviction of an unbounded run. In a bounded cache the
+    /// momentary overshoot before the capacity check evicts is not counted.
+    pub fn peak_occupancy(&self) -> usize {
+        self.peak_occupancy
+    }
+
+    //len() including overdue objects
+    fn stored_len(&self) -> usize {
+        self.content_map.len() + self.pinned.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Same as [`LeaseCache::total_size`].
+    pub fn get_cache_consumption(&self) -> usize {
+        self.total_size()
+        // self.cache_consumption
+    }
+
+    /// Sum of the sizes of the objects in the cache, which is what the
+    /// capacity bounds. Equal to `len()` unless
+    /// [`LeaseCache::insert_sized`] was used.
+    pub fn total_size(&self) -> usize {
+        let overdue_size: usize = self
+            .expiring_map
+            .range(..=self.current_time)
+            .flat_map(|(_, bucket)| bucket)
+            .map(|obj_id| self.size_of(obj_id))
+            .sum();
+        self.stored_size - overdue_size
+    }
+
+    pub fn remove(&mut self, obj_id: &Obj) {
+        let expiration = self.remove_entry(obj_id);
+        self.record_premature(expiration);
+        self.notify_evict(obj_id, EvictReason::Manual);
+    }
+
+    /// Removes every object of `obj_ids` that is in the cache, skipping the
+    /// rest, and returns how many were removed.
+    pub fn remove_many<I: IntoIterator<Item = Obj>>(&mut self, obj_ids: I) -> usize {
+        let mut removed = 0;
+        for obj_id in obj_ids {
+            if self.contains(&obj_id) {
+                self.remove(&obj_id);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    //drops obj_id from every internal structure, panicking if it is absent
+    //returns the expiration obj_id had, None if it was pinned
+    fn remove_entry(&mut self, obj_id: &Obj) -> Option<T> {
+        let expiration = if self.pinned.remove(obj_id) {
+            None
+        } else {
+            let expiration = self.content_map.remove(obj_id).unwrap();
+            self.unschedule(obj_id, expiration);
+            Some(expiration)
+        };
+        self.untrack(obj_id);
+        // self.cache_consumption -= 1;
+        expiration
+    }
+
+    //counts a victim removed before the end of its lease by how much lease it had left
+    fn record_premature(&mut self, expiration: Option<T>) {
+        if let Some(expiration) = expiration.filter(|&expiration| expiration > self.current_time) {
+            *self
+                .premature_evictions
+                .entry(expiration - self.current_time)
+                .or_default() += 1;
+        }
+    }
+
+    /// Starts or stops counting how often each object is force-evicted, to
+    /// check that capacity evictions are not biased towards some objects.
+    /// Stopping discards the counts.
+    pub fn set_track_force_evictions(&mut self, track_force_evictions: bool) {
+        match (track_force_evictions, self.force_evictions.is_some()) {
+            (true, false) => self.force_evictions = Some(HashMap::default()),
+            (false, true) => self.force_evictions = None,
+            _ => (),
+        }
+    }
+
+    /// Number of times each object was force-evicted since tracking started
+    /// or the last [`LeaseCache::reset_stats`], None while not tracked.
+    /// Objects never force-evicted are left out.
+    pub fn force_evict_distribution(&self) -> Option<&HashMap<Obj, u64, S>> {
+        self.force_evictions.as_ref()
+    }
+
+    fn record_force_eviction(&mut self, obj_id: &Obj) {
+        if let Some(force_evictions) = self.force_evictions.as_mut() {
+            *force_evictions.entry(obj_id.clone()).or_default() += 1;
+        }
+    }
+
+    /// Counts the objects force-evicted or removed while their lease was
+    /// still running, by the lease they had left, since creation or the
+    /// last [`LeaseCache::reset_stats`]. Shows how early capacity pressure
+    /// cuts leases short. Pinned victims are not counted.
+    pub fn premature_eviction_histogram(&self) -> &BTreeMap<T, u64> {
+        &self.premature_evictions
+    }
+
+    pub fn hit_count(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn miss_count(&self) -> u64 {
+        self.misses
+    }
+
+    /// Number of accesses seen by `update` since creation or the last
+    /// [`LeaseCache::reset_stats`].
+    pub fn access_count(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    /// Fraction of accesses that missed, or 0.0 before any access.
+    pub fn miss_ratio(&self) -> f64 {
+        match self.access_count() {
+            0 => 0.0,
+            accesses => self.misses as f64 / accesses as f64,
+        }
+    }
+
+    /// Number of objects whose lease ran out, including leases of 0 that
+    /// dropped a cached object.
+    pub fn expired_count(&self) -> u64 {
+        self.eviction_stats.expired
+    }
+
+    /// Number of objects force-evicted to make room.
+    pub fn capacity_evicted_count(&self) -> u64 {
+        self.eviction_stats.capacity
+    }
+
+    /// Number of objects removed with [`LeaseCache::remove`] and friends.
+    pub fn manually_removed_count(&self) -> u64 {
+        self.eviction_stats.manual
+    }
+
+    /// All three eviction counters at once, counted since creation or the
+    /// last [`LeaseCache::reset_stats`]. Clearing the cache does not count.
+    pub fn eviction_stats(&self) -> EvictionStats {
+        self.eviction_stats
+    }
+
+    /// Lets the next `accesses` accesses fill the cache as usual but leave
+    /// the hit/miss counters alone, so cold-start misses are not measured.
+    pub fn warmup(&mut self, accesses: u64) -> &mut Self {
+        self.warmup_remaining = accesses;
+        self
+    }
+
+    /// Zeroes the hit/miss and eviction counters, the running average
+    /// occupancy and any access frequencies and hotspots without touching
+    /// the cache contents.
+    pub fn reset_stats(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+        self.eviction_stats = EvictionStats::default();
+        self.premature_evictions.clear();
+        if let Some(frequencies) = self.frequencies.as_mut() {
+            frequencies.clear();
+        }
+        if let Some(force_evictions) = self.force_evictions.as_mut() {
+            force_evictions.clear();
+        }
+        self.occupancy_area = 0;
+        self.occupancy_ticks = 0;
+        self.hotspots.clear();
+    }
+
+    /// Turns the hit/miss counters on or off. They are on by default; while
+    /// off, accesses leave the counters and any pending warmup alone.
+    pub fn set_track_stats(&mut self, track_stats: bool) {
+        self.track_stats = track_stats;
+    }
+
+    /// Starts or stops counting the accesses of every object. Stopping
+    /// discards the counts.
+    pub fn set_track_frequency(&mut self, track_frequency: bool) {
+        match (track_frequency, self.frequencies.is_some()) {
+            (true, false) => self.frequencies = Some(HashMap::default()),
+            (false, true) => self.frequencies = None,
+            _ => (),
+        }
+    }
+
+    /// How many accesses `obj_id` has had since frequency tracking was
+    /// turned on or the last [`LeaseCache::reset_stats`], or None while
+    /// tracking is off.
+    pub fn access_frequency(&self, obj_id: &Obj) -> Option<u64> {
+        self.frequencies
+            .as_ref()
+            .map(|frequencies| frequencies.get(obj_id).copied().unwrap_or(0))
+    }
+
+    /// Time-weighted average of `len()` over every tick the clock has
+    /// advanced since creation or the last [`LeaseCache::reset_stats`], or
+    /// 0.0 if it has not moved.
+    pub fn average_occupancy(&self) -> f64 {
+        match self.occupancy_ticks {
+            0 => 0.0,
+            ticks => self.occupancy_area as f64 / ticks as f64,
+        }
+    }
+
+    /// Empties the cache and resets the clock to 0, keeping the allocated
+    /// capacity of the internal hash maps for reuse.
+    pub fn clear(&mut self) {
+        self.clear_preserving_time();
+        self.current_time = T::ZERO;
+        if let Some(interval) = self.sample_interval {
+            self.next_sample = interval;
+        }
+    }
+
+    /// Puts the cache back into the state it was built in, for repeating an
+    /// experiment: empties it like [`LeaseCache::clear`], zeroes the stats,
+    /// drops the occupancy samples and tick log and re-seeds the RNG with
+    /// the seed it was created with, or from entropy if it had none.
+    /// Capacity, clock mode, granularity, the eviction policy and the other
+    /// settings are kept.
+    pub fn reset(&mut self) {
+        self.clear();
+        self.reset_stats();
+        self.occupancy_samples.clear();
+        self.clear_tick_log();
+        self.rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+    }
+
+    /// Like [`LeaseCache::clear`], but leaves `current_time` where it is.
+    pub fn clear_preserving_time(&mut self) {
+        self.expiring_map.clear();
+        self.content_map.clear();
+        self.pinned.clear();
+        self.live_objects.clear();
+        self.sizes.clear();
+        self.stored_size = 0;
+        self.peak_occupancy = 0;
+        self.last_access.clear();
+        self.last_evicted = None;
+        if let Some(policy) = self.eviction_policy.as_mut() {
+            policy.on_clear();
+        }
+    }
+
+    /// Empties the cache like [`LeaseCache::clear_preserving_time`], yielding
+    /// every object it held with its time until eviction, in no particular
+    /// order. Pinned objects come with `T::MAX` since they never expire. The
+    /// eviction callback is not called.
+    pub fn drain(&mut self) -> impl Iterator<Item = (Obj, T)> {
+        self.take_contents().into_iter()
+    }
+
+    //the contents drain yields, leaving the cache empty
+    fn take_contents(&mut self) -> Vec<(Obj, T)> {
+        let current_time = self.current_time;
+        let contents = std::mem::take(&mut self.content_map)
+            .into_iter()
+            .filter(|&(_, expiration)| expiration > current_time)
+            .map(|(obj_id, expiration)| (obj_id, expiration - current_time))
+            .chain(
+                std::mem::take(&mut self.pinned)
+                    .into_iter()
+                    .map(|obj_id| (obj_id, T::MAX)),
+            )
+            .collect();
+        self.clear_preserving_time();
+        contents
+    }
+
+    /// Starts recording `(current_time, len())` every `interval` ticks,
+    /// counted from now. A single [`LeaseCache::advance_time_by`] that
+    /// covers several sample points records only one sample, at its end.
+    pub fn enable_occupancy_sampling(&mut self, interval: T) {
+        assert!(interval > T::ZERO, "sampling interval must be positive");
+        self.sample_interval = Some(interval);
+        self.next_sample = self.current_time.saturating_add(interval);
+    }
+
+    /// The samples recorded since [`LeaseCache::enable_occupancy_sampling`],
+    /// oldest first.
+    pub fn occupancy_samples(&self) -> &[(T, usize)] {
+        &self.occupancy_samples
+    }
+
+    /// Starts recording every expiration bucket holding more than
+    /// `threshold` objects when it is drained by the clock, to catch lease
+    /// assignments that pile objects onto a single expiration time.
+    pub fn set_hotspot_threshold(&mut self, threshold: usize) {
+        self.hotspot_threshold = Some(threshold);
+    }
+
+    /// `(expiration, size)` of every bucket over the hotspot threshold
+    /// drained since [`LeaseCache::set_hotspot_threshold`] or the last
+    /// [`LeaseCache::reset_stats`], oldest first.
+    pub fn hotspots(&self) -> &[(T, usize)] {
+        &self.hotspots
+    }
+
+    /// Gives a leased object one reprieve when its lease runs out: if it was
+    /// accessed at most `window` ticks before its expiration, it stays for
+    /// `grace_lease` more ticks instead of being evicted. Only accesses
+    /// through [`LeaseCache::update`] and its variants count, and the
+    /// reprieve is used up until the object is accessed again.
+    pub fn enable_second_chance(&mut self, window: T, grace_lease: T) {
+        assert!(grace_lease > T::ZERO, "grace lease must be positive");
+        self.second_chance = Some((window, grace_lease));
+    }
+
+    /// Turns second chances off; objects expire at the end of their lease.
+    pub fn disable_second_chance(&mut self) {
+        self.second_chance = None;
+        self.last_access.clear();
+    }
+
+    //moves the objects of expiring that earned a second chance to a grace lease from now
+    fn grant_second_chances(&mut self, expiring: &mut HashSet<Obj, S>, due: T) {
+        let Some((window, grace_lease)) = self.second_chance else {
+            return;
+        };
+        let reprieved: Vec<Obj> = expiring
+            .iter()
+            .filter(|obj_id| {
+                self.last_access
+                    .get(obj_id)
+                    .is_some_and(|&accessed| due.saturating_sub(accessed) <= window)
+            })
+            .cloned()
+            .collect();
+        let expiration = self.coarsen(self.current_time.saturating_add(grace_lease));
+        for obj_id in reprieved {
+            expiring.remove(&obj_id);
+            self.last_access.remove(&obj_id);
+            self.content_map.insert(obj_id.clone(), expiration);
+            self.expiring_map
+                .entry(expiration)
+                .or_default()
+                .insert(obj_id);
+        }
+    }
+
+    /// Makes `cache_access` give `obj_id` a lease of `lease` whenever an
+    /// access of it asks for a lease of 0, instead of dropping it. Other
+    /// leases, and direct calls such as [`LeaseCache::update`], are not
+    /// affected.
+    pub fn set_default_lease(&mut self, obj_id: Obj, lease: T) {
+        self.default_leases.insert(obj_id, lease);
+    }
+
+    pub fn remove_default_lease(&mut self, obj_id: &Obj) {
+        self.default_leases.remove(obj_id);
+    }
+
+    /// The lease set with [`LeaseCache::set_default_lease`], if any.
+    pub fn default_lease(&self, obj_id: &Obj) -> Option<T> {
+        self.default_leases.get(obj_id).copied()
+    }
+
+    //the lease cache_access applies for an access of obj_id asking for lease
+    fn access_lease(&self, obj_id: &Obj, lease: T) -> T {
+        if lease != T::ZERO {
+            return lease;
+        }
+        self.default_lease(obj_id).unwrap_or(lease)
+    }
+
+    /// Starts recording a [`TickRecord`] for every advance of the clock,
+    /// e.g. to animate a replay. Advances that neither move the clock nor
+    /// expire anything are not recorded.
+    pub fn enable_tick_log(&mut self) {
+        self.tick_log.get_or_insert_with(Vec::new);
+    }
+
+    /// Stops recording and discards the log.
+    pub fn disable_tick_log(&mut self) {
+        self.tick_log = None;
+    }
+
+    /// The records since [`LeaseCache::enable_tick_log`] or the last
+    /// [`LeaseCache::clear_tick_log`], oldest first. Empty while not logging.
+    pub fn tick_log(&self) -> &[TickRecord<Obj, T>] {
+        self.tick_log.as_deref().unwrap_or_default()
+    }
+
+    /// Empties the log but keeps recording.
+    pub fn clear_tick_log(&mut self) {
+        if let Some(tick_log) = self.tick_log.as_mut() {
+            tick_log.clear();
+        }
+    }
+
+    pub fn advance_time(&mut self) -> HashSet<Obj, S> {
+        self.advance_time_by(T::ONE)
+    }
+
+    /// Advances the clock by `steps` ticks at once and returns the union of
+    /// every object that expired on the way. Only buckets that actually hold
+    /// objects are visited, so the cost does not depend on `steps`.
+    ///
+    /// Every bucket due at or before the new time is drained, not just the
+    /// one at exactly that tick, so an expiration that has already fallen
+    /// behind the clock is still honoured on the next advance.
+    pub fn advance_time_by(&mut self, steps: T) -> HashSet<Obj, S> {
+        let start = self.current_time;
+        self.current_time = self.current_time.saturating_add(steps);
+        //occupancy only changes at bucket boundaries, integrate it piecewise
+        let mut occupancy = self.stored_len() as u128;
+        let mut occupied_since = start;
+        let mut expired = HashSet::default();
+        //expired in bucket order, only kept for the tick log
+        let mut evicted = Vec::new();
+        while let Some(bucket) = self.expiring_map.first_entry() {
+            if *bucket.key() > self.current_time {
+                break;
+            }
+            let expiration = (*bucket.key()).max(start);
+            self.occupancy_area += occupancy * (expiration - occupied_since).as_u128();
+            occupied_since = expiration;
+            let (due, mut expiring) = bucket.remove_entry();
+            if self
+                .hotspot_threshold
+                .is_some_and(|threshold| expiring.len() > threshold)
+            {
+                self.hotspots.push((due, expiring.len()));
+            }
+            self.grant_second_chances(&mut expiring, due);
+            occupancy -= expiring.len() as u128;
+            //removing expiring from content map
+            for obj_id in &expiring {
+                //pinned objects are never scheduled, so they cannot expire
+                debug_assert!(!self.pinned.contains(obj_id), "pinned {:?} expired", obj_id);
+                self.content_map.remove(obj_id);
+                self.untrack(obj_id);
+                self.notify_evict(obj_id, EvictReason::Expired);
+            }
+            if self.tick_log.is_some() {
+                evicted.extend(expiring.iter().cloned());
+            }
+            expired.extend(expiring);
+        }
+        if self.current_time > start || !evicted.is_empty() {
+            let occupancy = self.len();
+            if let Some(tick_log) = self.tick_log.as_mut() {
+                tick_log.push(TickRecord {
+                    time: self.current_time,
+                    evicted,
+                    occupancy,
+                });
+            }
+        }
+        self.occupancy_area += occupancy * (self.current_time - occupied_since).as_u128();
+        self.occupancy_ticks += (self.current_time - start).as_u128();
+        if let Some(interval) = self.sample_interval {
+            if self.current_time >= self.next_sample {
+                self.occupancy_samples.push((self.current_time, self.len()));
+                self.next_sample = self.current_time.saturating_add(interval);
+            }
+        }
+
+        return expired;
+    }
+
+    /// Shortens the remaining lease of every object by `delta` ticks without
+    /// moving the clock. Objects whose lease would run out at or before the
+    /// current time are evicted as expired and returned. The new expirations
+    /// are rounded up to the granularity, as in [`LeaseCache::scale_leases`].
+    pub fn age_all(&mut self, delta: T) -> HashSet<Obj, S> {
+        let mut expired = HashSet::default();
+        //subtracting a constant keeps the order of buckets but may round some together
+        for (expiration, bucket) in std::mem::take(&mut self.expiring_map) {
+            let aged = expiration.saturating_sub(delta);
+            if aged <= self.current_time {
+                for obj_id in &bucket {
+                    self.content_map.remove(obj_id);
+                    self.untrack(obj_id);
+                    self.notify_evict(obj_id, EvictReason::Expired);
+                }
+                expired.extend(bucket);
+            } else {
+                let aged = self.coarsen(aged);
+                for obj_id in &bucket {
+                    *self.content_map.get_mut(obj_id).unwrap() = aged;
+                }
+                self.expiring_map.entry(aged).or_default().extend(bucket);
+            }
+        }
+        expired
+    }
+
+    /// Multiplies the remaining lease of every leased object by `factor`,
+    /// rounding to the nearest tick, e.g. 0.5 halves every lifetime and 2.0
+    /// doubles it. Objects whose lease rounds to 0, all of them for a factor
+    /// of 0, are evicted as expired and returned. Pinned objects are left
+    /// alone. Panics if `factor` is negative or NaN.
+    pub fn scale_leases(&mut self, factor: f64) -> HashSet<Obj, S> {
+        assert!(factor >= 0.0, "lease scale factor must be non-negative");
+        let mut expired = HashSet::default();
+        //scaling keeps the order of buckets but may round some together
+        for (expiration, bucket) in std::mem::take(&mut self.expiring_map) {
+            let remaining = expiration.saturating_sub(self.current_time).as_u128();
+            let scaled = T::saturating_from_u128((remaining as f64 * factor).round() as u128);
+            if scaled == T::ZERO {
+                for obj_id in &bucket {
+                    self.content_map.remove(obj_id);
+                    self.untrack(obj_id);
+                    self.notify_evict(obj_id, EvictReason::Expired);
+                }
+                expired.extend(bucket);
+            } else {
+                let scaled = self.coarsen(self.current_time.saturating_add(scaled));
+                for obj_id in &bucket {
+                    *self.content_map.get_mut(obj_id).unwrap() = scaled;
+                }
+                self.expiring_map.entry(scaled).or_default().extend(bucket);
+            }
+        }
+        expired
+    }
+
+    /// Advances the clock to the absolute time `target`, like
+    /// [`LeaseCache::advance_time_by`]. A target that is not in the future
+    /// leaves the cache untouched and returns an empty set.
+    pub fn advance_time_to(&mut self, target: T) -> HashSet<Obj, S> {
+        if target <= self.current_time {
+            return HashSet::default();
+        }
+        self.advance_time_by(target - self.current_time)
+    }
+
+    pub fn remove_random_element<K, V>(map: &mut HashMap<K, V>) -> Option<(K, V)>
+    where
+        K: std::hash::Hash + Eq + Clone,
+        V: Clone,
+    {
+        if let Some((key, val)) = map.clone().iter().choose(&mut rand::thread_rng()) {
+            map.remove(key);
+            return Some((key.clone(), val.clone()));
+        }
+        None
+    }
+
+    /// Evicts the victim picked by the eviction policy, or a uniformly random
+    /// object if there is no policy or it picks an object that is not cached.
+    /// Panics if there is nothing to evict, i.e. on an empty cache or when
+    /// the policy picks no victim, see [`LeaseCache::try_force_evict`].
+    pub fn force_evict(&mut self) -> Obj {
+        self.try_force_evict()
+            .expect("force_evict found nothing to evict")
+    }
+
+    /// Like [`LeaseCache::force_evict`], but returns None instead of
+    /// panicking when there is nothing to evict.
+    pub fn try_force_evict(&mut self) -> Option<Obj> {
+        // println!("content map before {:?}", self.content_map);
+
+        //overdue objects have already expired, drain them so none is picked
+        self.advance_time_by(T::ZERO);
+        //the policy is taken out so it can look at the cache it belongs to
+        let obj_id = match self.eviction_policy.take() {
+            Some(mut policy) => {
+                let chosen = policy.choose_victim(self);
+                self.eviction_policy = Some(policy);
+                //None means the policy has nothing it is willing to evict
+                match chosen? {
+                    obj_id if self.is_stored(&obj_id) => obj_id,
+                    _ => self.live_objects.random(&mut self.rng).cloned()?,
+                }
+            }
+            None => self.live_objects.random(&mut self.rng).cloned()?,
+        };
+        let expiration = self.remove_entry(&obj_id);
+        self.record_premature(expiration);
+        self.record_force_eviction(&obj_id);
+        self.notify_evict(&obj_id, EvictReason::Capacity);
+        self.last_evicted = Some(obj_id.clone());
+        Some(obj_id)
+    }
+
+    /// Evicts up to `n` objects in one call, stopping early once the cache is
+    /// empty. The victims and RNG draws are the same as those of `n` calls to
+    /// [`LeaseCache::force_evict`], in order.
+    pub fn force_evict_n(&mut self, n: usize) -> Vec<Obj> {
+        (0..n).map_while(|_| self.try_force_evict()).collect()
+    }
+
+    /// Folds `other` into this cache, e.g. to combine the caches of workers
+    /// that each replayed part of a trace. The clock moves to the later of
+    /// the two, expiring whatever ran out by then in either cache. An object
+    /// both caches hold keeps the later of its two expirations and this
+    /// cache's size; a pinned copy on either side wins over any lease. The
+    /// rest of `other` is added as is, and its hit, miss and eviction
+    /// counters are added to this cache's; its peak occupancy and premature
+    /// eviction histogram are dropped. Expirations taken from `other` are
+    /// rounded to this cache's granularity. Capacity is not enforced until
+    /// the next access.
+    pub fn merge(&mut self, mut other: LeaseCache<Obj, T, S>) {
+        self.advance_time_to(other.current_time);
+        other.advance_time_to(self.current_time);
+        //the clocks may already have been equal, drain what is overdue
+        self.advance_time_by(T::ZERO);
+        other.advance_time_by(T::ZERO);
+        for (obj_id, expiration) in std::mem::take(&mut other.content_map) {
+            if self.pinned.contains(&obj_id) {
+                continue;
+            }
+            match self.content_map.get(&obj_id) {
+                Some(&own_expiration) if own_expiration >= expiration => (),
+                Some(_) => self.reschedule(&obj_id, self.coarsen(expiration)),
+                None => {
+                    let size = other.size_of(&obj_id);
+                    self.insert_sized(obj_id, expiration - self.current_time, size);
+                }
+            }
+        }
+        for obj_id in std::mem::take(&mut other.pinned) {
+            let size = other.size_of(&obj_id);
+            if !self.is_stored(&obj_id) && size != 1 {
+                self.sizes.insert(obj_id.clone(), size);
+            }
+            self.insert_pinned(obj_id);
+        }
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.eviction_stats.expired += other.eviction_stats.expired;
+        self.eviction_stats.capacity += other.eviction_stats.capacity;
+        self.eviction_stats.manual += other.eviction_stats.manual;
+    }
+
+    /// Checkpoints the cache contents, clock and hit/miss counters, e.g. to
+    /// run several experiments from the same state.
+    pub fn snapshot(&self) -> CacheSnapshot<Obj, T, S> {
+        CacheSnapshot {
+            expiring_map: self.expiring_map.clone(),
+            current_time: self.current_time,
+            content_map: self.content_map.clone(),
+            pinned: self.pinned.clone(),
+            live_objects: self.live_objects.clone(),
+            sizes: self.sizes.clone(),
+            stored_size: self.stored_size,
+            last_access: self.last_access.clone(),
+            last_evicted: self.last_evicted.clone(),
+            peak_occupancy: self.peak_occupancy,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    /// Rolls the cache back to `snapshot`. The capacity, eviction RNG,
+    /// callback and occupancy samples are left as they are. The eviction
+    /// policy is cleared and then told about every restored object.
+    pub fn restore(&mut self, snapshot: &CacheSnapshot<Obj, T, S>) {
+        self.expiring_map.clone_from(&snapshot.expiring_map);
+        self.current_time = snapshot.current_time;
+        self.content_map.clone_from(&snapshot.content_map);
+        self.pinned.clone_from(&snapshot.pinned);
+        self.live_objects.clone_from(&snapshot.live_objects);
+        self.sizes.clone_from(&snapshot.sizes);
+        self.stored_size = snapshot.stored_size;
+        self.last_access.clone_from(&snapshot.last_access);
+        self.last_evicted.clone_from(&snapshot.last_evicted);
+        self.peak_occupancy = snapshot.peak_occupancy;
+        self.hits = snapshot.hits;
+        self.misses = snapshot.misses;
+        if let Some(policy) = self.eviction_policy.as_mut() {
+            policy.on_clear();
+            for obj_id in self.live_objects.iter() {
+                policy.on_insert(obj_id);
+            }
+        }
+    }
+
+    /// The total size of the objects the cache may hold, which is their
+    /// number unless [`LeaseCache::insert_sized`] is used. None if unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.cache_size
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.cache_size.is_some()
+    }
+
+    /// How much more size fits before the capacity is reached, 0 if it is
+    /// already exceeded, None if unbounded.
+    pub fn remaining_capacity(&self) -> Option<usize> {
+        self.cache_size
+            .map(|cache_size| cache_size.saturating_sub(self.total_size()))
+    }
+
+    /// Like [`LeaseCache::insert`], but fails with [`CacheFull`] instead of
+    /// making room when a new object would take the cache over capacity.
+    /// Nothing is evicted either way. Renewing the lease of a cached object
+    /// always succeeds.
+    pub fn try_insert(&mut self, obj_id: Obj, lease: T) -> Result<(), CacheFull> {
+        if !self.contains(&obj_id) && lease > T::ZERO && self.remaining_capacity() == Some(0) {
+            return Err(CacheFull);
+        }
+        self.insert(obj_id, lease);
+        Ok(())
+    }
+
+    /// Sets the capacity like [`CacheSim::set_capacity`], but also
+    /// force-evicts right away until the cache fits, returning the victims.
+    pub fn set_capacity_now(&mut self, cache_size: usize) -> Vec<Obj> {
+        self.cache_size = Some(cache_size);
+        self.enforce_capacity()
+    }
+
+    /// Makes the capacity check force-evict `batch_size` victims at a time,
+    /// with [`LeaseCache::force_evict_n`], until the cache fits again, so it
+    /// may evict up to `batch_size - 1` more objects than strictly needed.
+    /// `None`, the default, evicts just the excess: all of it in one batch
+    /// with unit sizes and one victim at a time with sized objects.
+    /// Panics on a batch size of 0.
+    pub fn set_eviction_batch_size(&mut self, batch_size: Option<usize>) {
+        assert!(
+            batch_size != Some(0),
+            "eviction batch size must be positive"
+        );
+        self.eviction_batch = batch_size;
+    }
+
+    //force-evicts until the cache is within capacity, returning the victims
+    fn enforce_capacity(&mut self) -> Vec<Obj> {
+        let mut victims = Vec::new();
+        if let Some(cache_size) = self.cache_size {
+            while self.total_size() > cache_size {
+                //with unit sizes the excess is exactly the number of victims needed
+                let batch = match self.eviction_batch {
+                    Some(batch_size) => batch_size,
+                    None if self.sizes.is_empty() => self.total_size() - cache_size,
+                    None => 1,
+                };
+                let evicted = self.force_evict_n(batch);
+                //nothing left to evict, e.g. after removals the sizes missed
+                if evicted.is_empty() {
+                    break;
+                }
+                victims.extend(evicted);
+            }
+            self.note_peak_occupancy();
+        }
+        victims
+    }
+
+    /// The object most recently force-evicted for capacity, whether by the
+    /// capacity check in `cache_access` or a direct call. Expirations and
+    /// explicit removals do not count.
+    pub fn last_evicted(&self) -> Option<&Obj> {
+        self.last_evicted.as_ref()
+    }
+
+    /// Evicts the object whose lease runs out soonest, the one that would
+    /// have left the cache first anyway.
+    ///
+    /// Ties go to the object with the lowest slot in the cache's internal
+    /// index of live objects. Slots are handed out in insertion order and
+    /// only shuffled by removals (the last object moves into the freed
+    /// slot), so the choice depends only on the sequence of operations and
+    /// is the same on every run. Pinned objects never expire, so they are
+    /// only picked once no leased object is left. Objects whose lease has
+    /// already run out are drained as expired first rather than picked.
+    ///
+    /// Panics on an empty cache, see [`LeaseCache::try_evict_earliest`].
+    pub fn evict_earliest(&mut self) -> Obj {
+        self.try_evict_earliest()
+            .expect("evict_earliest called on an empty cache")
+    }
+
+    /// Like [`LeaseCache::evict_earliest`], but returns None instead of
+    /// panicking when there is nothing to evict.
+    pub fn try_evict_earliest(&mut self) -> Option<Obj> {
+        //overdue objects have already expired, drain them so none is picked
+        self.advance_time_by(T::ZERO);
+        let obj_id = self.earliest_victim()?;
+        let expiration = self.remove_entry(&obj_id);
+        self.record_premature(expiration);
+        self.record_force_eviction(&obj_id);
+        self.notify_evict(&obj_id, EvictReason::Capacity);
+        self.last_evicted = Some(obj_id.clone());
+        Some(obj_id)
+    }
+
+    //the object evict_earliest would pick, None on an empty cache
+    pub(crate) fn earliest_victim(&self) -> Option<Obj> {
+        let candidates = match self.expiring_map.first_key_value() {
+            Some((_, bucket)) => bucket,
+            None => &self.pinned,
+        };
+        candidates
+            .iter()
+            .min_by_key(|obj_id| self.live_objects.position(obj_id))
+            .cloned()
+    }
+
+    //same as earliest_victim, but from the bucket that expires last and
+    //never a pinned object, None once only pinned objects are left
+    pub(crate) fn latest_victim(&self) -> Option<Obj> {
+        let (_, candidates) = self.expiring_map.last_key_value()?;
+        candidates
+            .iter()
+            .min_by_key(|obj_id| self.live_objects.position(obj_id))
+            .cloned()
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits, S: CacheHasher> LeaseCache<Obj, T, S> {
+    /// Same as [`CacheSim::cache_access`], but also returns the objects
+    /// force-evicted to bring the cache back within capacity.
+    /// A cache without a capacity never evicts.
+    pub fn cache_access_tracked(
+        &mut self,
+        access: TaggedObjectId<T, Obj>,
+    ) -> (AccessResult, Vec<Obj>) {
+        let TaggedObjectId(lease, obj_id) = access;
+        let lease = self.access_lease(&obj_id, lease);
+        let cache_result = self.update(&obj_id, lease);
+        (cache_result, self.enforce_capacity())
+    }
+
+    /// Same as [`CacheSim::cache_access`], but reports everything the access
+    /// did, see [`AccessOutcome`].
+    pub fn cache_access_detailed(&mut self, access: TaggedObjectId<T, Obj>) -> AccessOutcome<Obj> {
+        let TaggedObjectId(lease, obj_id) = access;
+        let lease = self.access_lease(&obj_id, lease);
+        let result = self.update(&obj_id, lease);
+        let was_insertion = matches!(result, AccessResult::Miss) && self.is_stored(&obj_id);
+        AccessOutcome {
+            result,
+            evicted: self.enforce_capacity(),
+            was_insertion,
+        }
+    }
+}
+
+/// Everything one access did, as returned by
+/// [`LeaseCache::cache_access_detailed`].
+#[derive(Debug)]
+pub struct AccessOutcome<Obj: ObjIdTraits> {
+    pub result: AccessResult,
+    /// objects force-evicted to bring the cache back within capacity
+    pub evicted: Vec<Obj>,
+    /// whether the access missed and brought the object into the cache; a
+    /// miss with a lease of 0 does not, and the object may be among
+    /// `evicted` right away
+    pub was_insertion: bool,
+}
+
+/// In-memory checkpoint of a [`LeaseCache`]: its contents, clock, hit/miss
+/// counters, peak occupancy, last victim and second-chance access times. Taken by [`LeaseCache::snapshot`] and applied with
+/// [`LeaseCache::restore`].
+#[derive(Debug, Clone)]
+pub struct CacheSnapshot<Obj: ObjIdTraits, T: Time = usize, S = RandomState> {
+    expiring_map: BTreeMap<T, HashSet<Obj, S>>,
+    current_time: T,
+    content_map: HashMap<Obj, T, S>,
+    pinned: HashSet<Obj, S>,
+    live_objects: IndexedSet<Obj, S>,
+    sizes: HashMap<Obj, usize, S>,
+    stored_size: usize,
+    last_access: HashMap<Obj, T, S>,
+    last_evicted: Option<Obj>,
+    peak_occupancy: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl<Obj: ObjIdTraits, T: Time, S> CacheSnapshot<Obj, T, S> {
+    pub fn current_time(&self) -> T {
+        self.current_time
+    }
+
+    pub fn len(&self) -> usize {
+        self.content_map.len() + self.pinned.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content_map.is_empty() && self.pinned.is_empty()
+    }
+}
+
+/// Two caches are equal when they are at the same time, have the same
+/// capacity and hold the same objects with the same expirations and pins.
+/// Objects whose lease has run out but that are still waiting to be drained
+/// are ignored, and stats, callbacks, policies and other settings are not
+/// compared.
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> PartialEq for LeaseCache<Obj, T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.current_time == other.current_time
+            && self.cache_size == other.cache_size
+            && self.pinned == other.pinned
+            && self.len() == other.len()
+            && self
+                .iter()
+                .all(|(obj_id, lease)| other.time_until_eviction(obj_id) == Some(lease))
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> Eq for LeaseCache<Obj, T, S> {}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> Clone for LeaseCache<Obj, T, S> {
+    /// The clone starts without an eviction callback, since boxed closures
+    /// cannot be cloned. The eviction policy is cloned along with the rest.
+    fn clone(&self) -> Self {
+        LeaseCache {
+            expiring_map: self.expiring_map.clone(),
+            current_time: self.current_time,
+            clock_mode: self.clock_mode,
+            granularity: self.granularity,
+            content_map: self.content_map.clone(),
+            cache_size: self.cache_size,
+            rng: self.rng.clone(),
+            seed: self.seed,
+            pinned: self.pinned.clone(),
+            live_objects: self.live_objects.clone(),
+            sizes: self.sizes.clone(),
+            stored_size: self.stored_size,
+            peak_occupancy: self.peak_occupancy,
+            hits: self.hits,
+            misses: self.misses,
+            eviction_stats: self.eviction_stats,
+            premature_evictions: self.premature_evictions.clone(),
+            last_evicted: self.last_evicted.clone(),
+            warmup_remaining: self.warmup_remaining,
+            track_stats: self.track_stats,
+            frequencies: self.frequencies.clone(),
+            force_evictions: self.force_evictions.clone(),
+            sample_interval: self.sample_interval,
+            next_sample: self.next_sample,
+            occupancy_samples: self.occupancy_samples.clone(),
+            hotspot_threshold: self.hotspot_threshold,
+            eviction_batch: self.eviction_batch,
+            hotspots: self.hotspots.clone(),
+            second_chance: self.second_chance,
+            last_access: self.last_access.clone(),
+            default_leases: self.default_leases.clone(),
+            tick_log: self.tick_log.clone(),
+            occupancy_area: self.occupancy_area,
+            occupancy_ticks: self.occupancy_ticks,
+            on_evict: None,
+            eviction_policy: self
+                .eviction_policy
+                .as_ref()
+                .map(|policy| policy.clone_box()),
+            admission_policy: self
+                .admission_policy
+                .as_ref()
+                .map(|policy| policy.clone_box()),
+        }
+    }
+}
+
+/// Consumes the cache, yielding what [`LeaseCache::drain`] would.
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> IntoIterator for LeaseCache<Obj, T, S> {
+    type Item = (Obj, T);
+    type IntoIter = std::vec::IntoIter<(Obj, T)>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.take_contents().into_iter()
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> Default for LeaseCache<Obj, T, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits, S: CacheHasher> CacheSim<TaggedObjectId<T, Obj>>
+    for LeaseCache<Obj, T, S>
+{
+    /// returns (total_access_count, miss_count)
+    /// input is an iterator of TaggedObjectId<Lease, ObjId>
+    fn cache_access(&mut self, access: TaggedObjectId<T, Obj>) -> abstract_cache::AccessResult {
+        let (cache_result, _victims) = self.cache_access_tracked(access);
+        return cache_result;
+    }
+
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.cache_size = Some(cache_size);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_time_till_eviction() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 2);
+        lease_cache.insert(3, 3);
+        // lease_cache.cache_consumption = 3;
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(2));
+        assert_eq!(lease_cache.time_until_eviction(&3), Some(3));
+        lease_cache.advance_time();
+
+        println!("{:?}", lease_cache.time_until_eviction(&1));
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(1));
+        assert_eq!(lease_cache.time_until_eviction(&3), Some(2));
+    }
+
+    #[test]
+    fn test_lease_zero() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.update(&1, 4);
+        lease_cache.update(&2, 0);
+        lease_cache.update(&1, 0);
+        assert_eq!(lease_cache.time_until_eviction(&1), None);
+        assert_eq!(lease_cache.time_until_eviction(&2), None);
+        assert!(!lease_cache.content_map.contains_key(&2));
+        assert!(!lease_cache.content_map.contains_key(&1));
+        assert!(lease_cache.get_cache_consumption() == 0);
+    }
+
+    #[test]
+    fn test_lease_cache_new() {
+        let lease_cache = LeaseCache::<usize>::new();
+        assert!(lease_cache.expiring_map.is_empty());
+        assert_eq!(lease_cache.current_time, 0);
+        assert_eq!(lease_cache.content_map.len(), 0);
+    }
+
+    #[test]
+    fn test_lease_cache_insert() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 2);
+        lease_cache.insert(3, 3);
+        assert!(lease_cache.content_map.contains_key(&1));
+        assert!(lease_cache.content_map.contains_key(&2));
+        assert!(lease_cache.content_map.contains_key(&3));
+        let mut expiration = lease_cache.content_map.get(&1).unwrap();
+        assert!(lease_cache.expiring_map[expiration].contains(&1));
+        expiration = lease_cache.content_map.get(&2).unwrap();
+        assert!(lease_cache.expiring_map[expiration].contains(&2));
+        expiration = lease_cache.content_map.get(&3).unwrap();
+        assert!(lease_cache.expiring_map[expiration].contains(&3));
+    }
+
+    #[test]
+    fn test_lease_cache_update() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        // Update the lease cache with obj_id 1 and index 1
+        lease_cache.update(&1, 1);
+        // Get the absolute index of obj_id 1 and release the immutable borrow
+        let expiration: usize = *lease_cache.content_map.get(&1).unwrap();
+        assert!(lease_cache.expiring_map[&expiration].contains(&1));
+        // Update the lease cache with obj_id 1 and new index 4
+        lease_cache.update(&1, 4);
+        assert!(lease_cache.content_map.keys().len() == 1);
+        // Get the old absolute index and assert it no longer contains obj_id 1
+        // let abs_index_old = abs_index; // Reuse the old index
+        println!("results {:?}", lease_cache.expiring_map.get(&expiration));
+        assert!(!lease_cache
+            .expiring_map
+            .get(&expiration)
+            .is_some_and(|bucket| bucket.contains(&1)));
+        assert!(lease_cache.content_map.keys().len() == 1);
+        // Get the new absolute index and assert it contains obj_id 1
+        let expiration_new = *lease_cache.content_map.get(&1).unwrap();
+        assert!(lease_cache.expiring_map[&expiration_new].contains(&1));
+    }
+
+    #[test]
+    fn test_lease_cache_dump_expiring() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        //test to make sure expiring objects are dumped correctly,
+        //this means that each time we dump we see the objects that the correct
+        //objects are expiring and the expiring index is incremented by one
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 2);
+        lease_cache.insert(3, 3);
+        // lease_cache.cache_consumption = 3;
+        let mut expiring = lease_cache.advance_time();
+        let mut expected = HashSet::new();
+        expected.insert(1);
+        assert_eq!(expiring, expected);
+        expiring = lease_cache.advance_time();
+        expected.insert(2);
+        expected.remove(&1);
+        assert_eq!(expiring, expected);
+        assert!(!lease_cache.content_map.contains_key(&1));
+        expiring = lease_cache.advance_time();
+        expected.insert(3);
+        expected.remove(&2);
+        assert_eq!(expiring, expected);
+        assert!(!lease_cache.content_map.contains_key(&2));
+        // assert_eq!(lease_cache.cache_consumption, 0);
+        assert!(lease_cache.content_map.is_empty())
+
+        //TODO: test that expiring index is incremented correctly at the boundry
+    }
+
+    #[allow(dead_code)]
+    fn test_lease_cache_force_evict() {
+        let epsilon = 0.1;
+        let num_iters = 100;
+        //we want to test that each object in the cache has an equal chance of being evicted
+        let mut num_obj1_evicted = 0;
+        let mut num_obj2_evicted = 0;
+        let mut num_obj3_evicted = 0;
+        for _ in 0..num_iters {
+            let mut lease_cache = LeaseCache::<usize>::new();
+            lease_cache.insert(1, 100000);
+            lease_cache.insert(2, 100000);
+            lease_cache.insert(3, 9);
+            let evicted_obj = lease_cache.force_evict();
+            match evicted_obj {
+                1 => num_obj1_evicted += 1,
+                2 => num_obj2_evicted += 1,
+                3 => num_obj3_evicted += 1,
+                _ => panic!("Invalid object evicted"),
+            }
+            // if i % 10 == 1 {
+            //     println!("{} ", i)
+            // }
+        }
+        //check that each object was evicted is within a small epsilon
+        let check_obj1 =
+            ((num_obj1_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
+        let check_obj2 =
+            ((num_obj2_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
+        let check_obj3 =
+            ((num_obj3_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
+        println!(
+            "eviction count: {} {} {}",
+            num_obj1_evicted, num_obj2_evicted, num_obj3_evicted
+        );
+        println!(
+            "eviction ratio: {} {} {}",
+            num_obj1_evicted as f64 / num_iters as f64,
+            num_obj2_evicted as f64 / num_iters as f64,
+            num_obj3_evicted as f64 / num_iters as f64
+        );
+        assert!(check_obj1 && check_obj2 && check_obj3);
+    }
+
+    #[test]
+    fn test_lease_cache_force_evict_string() {
+        let epsilon = 0.2;
+        let num_iters = 100;
+        //we want to test that each object in the cache has an equal chance of being evicted
+        let mut num_obj1_evicted = 0;
+        let mut num_obj2_evicted = 0;
+        let mut num_obj3_evicted = 0;
+        let obj_1 = "x1";
+        let obj_2 = "x2";
+        let obj_3 = "x3";
+        let mut lease_cache = LeaseCache::<String>::new();
+
+        for _i in 0..num_iters {
             lease_cache.insert(obj_1.to_string(), 100);
             lease_cache.insert(obj_2.to_string(), 100);
             lease_cache.insert(obj_3.to_string(), 9);
@@ -359,104 +2088,1642 @@ mod test {
                 o if o == obj_3 => num_obj3_evicted += 1,
                 _ => panic!("Invalid object evicted"),
             }
-            // if _i % 10 == 1 {
-            //     println!("{} ", _i)
-            // }
+            // if _i % 10 == 1 {
+            //     println!("{} ", _i)
+            // }
+        }
+        //check that each object was evicted is within a small epsilon
+        let check_obj1 =
+            ((num_obj1_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
+        let check_obj2 =
+            ((num_obj2_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
+        let check_obj3 =
+            ((num_obj3_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
+        println!(
+            "eviction count: {} {} {}",
+            num_obj1_evicted, num_obj2_evicted, num_obj3_evicted
+        );
+        println!(
+            "eviction ratio: {} {} {}",
+            num_obj1_evicted as f64 / num_iters as f64,
+            num_obj2_evicted as f64 / num_iters as f64,
+            num_obj3_evicted as f64 / num_iters as f64
+        );
+        assert!(check_obj1 && check_obj2 && check_obj3);
+    }
+
+    #[test]
+    fn test_remove_from_cache() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 2);
+        lease_cache.insert(3, 3);
+        // lease_cache.cache_consumption = 3;
+        lease_cache.remove(&1);
+        assert!(!lease_cache.content_map.contains_key(&1));
+        assert_eq!(lease_cache.get_cache_consumption(), 2);
+        lease_cache.remove(&2);
+        assert!(!lease_cache.content_map.contains_key(&2));
+        assert_eq!(lease_cache.get_cache_consumption(), 1);
+        lease_cache.remove(&3);
+        assert!(!lease_cache.content_map.contains_key(&3));
+        assert_eq!(lease_cache.get_cache_consumption(), 0);
+    }
+
+    #[test]
+    fn test_lease_one() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let obj_id = 1;
+        let _access_result = lease_cache.update(&obj_id, 1);
+        assert_eq!(lease_cache.time_until_eviction(&obj_id), Some(1));
+        lease_cache.advance_time();
+        println!("{:?}", lease_cache.time_until_eviction(&obj_id));
+        assert!(!lease_cache.contains(&obj_id));
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn test_lease_cache_update_lease_one() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let obj_id = 1;
+        let mut access_result = lease_cache.update(&obj_id, 1);
+        assert_eq!(access_result, AccessResult::Miss);
+        assert_eq!(lease_cache.time_until_eviction(&obj_id), Some(1));
+        assert!(lease_cache.contains(&obj_id));
+        access_result = lease_cache.update(&obj_id, 1);
+        assert_eq!(access_result, AccessResult::Hit);
+        assert_eq!(lease_cache.time_until_eviction(&obj_id), Some(1));
+        assert!(lease_cache.contains(&obj_id));
+        access_result = lease_cache.update(&2, 2);
+        assert_eq!(access_result, AccessResult::Miss);
+        assert_eq!(lease_cache.contains(&obj_id), false);
+    }
+
+    // #[test]
+    // fn test_lease_zero() {
+    //     let mut lease_cache = LeaseCache::<usize>::new();
+    //     lease_cache.update(&1, 2);
+    //     lease_cache.update(&2, 0);
+    //     assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
+    //     assert_eq!(lease_cache.time_until_eviction(&2), None);
+    //     assert!(!lease_cache.content_map.contains_key(&2));
+    // }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_wrap_around_lease_one() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.current_time = MAX_EXPIRING_VEC_SIZE - 1;
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 2);
+        lease_cache.insert(3, 3);
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(2));
+        assert_eq!(lease_cache.time_until_eviction(&3), Some(3));
+        lease_cache.advance_time();
+        assert_eq!(lease_cache.time_until_eviction(&1), None);
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(1));
+        assert_eq!(lease_cache.time_until_eviction(&3), Some(2));
+        lease_cache.advance_time();
+        assert_eq!(lease_cache.time_until_eviction(&1), None);
+        assert_eq!(lease_cache.time_until_eviction(&2), None);
+        assert_eq!(lease_cache.time_until_eviction(&3), Some(1));
+    }
+
+    #[test]
+    fn test_advance_time_by() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 3);
+        lease_cache.insert(3, 10);
+        let expiring = lease_cache.advance_time_by(5);
+        assert_eq!(expiring, HashSet::from([1, 2]));
+        assert_eq!(lease_cache.current_time, 5);
+        assert!(!lease_cache.contains(&1));
+        assert!(!lease_cache.contains(&2));
+        assert_eq!(lease_cache.time_until_eviction(&3), Some(5));
+        assert!(lease_cache.advance_time_by(0).is_empty());
+        assert_eq!(lease_cache.current_time, 5);
+        assert_eq!(lease_cache.advance_time_by(5), HashSet::from([3]));
+        assert_eq!(lease_cache.get_cache_consumption(), 0);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_advance_time_by_wrap_around() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.current_time = MAX_EXPIRING_VEC_SIZE - 2;
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 4);
+        assert_eq!(lease_cache.advance_time_by(3), HashSet::from([1]));
+        assert_eq!(lease_cache.current_time, MAX_EXPIRING_VEC_SIZE + 1);
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(1));
+    }
+
+    #[test]
+    fn test_force_evict_seeded() {
+        let mut lease_cache_a = LeaseCache::<usize>::with_rng(42);
+        let mut lease_cache_b = LeaseCache::<usize>::with_rng(42);
+        for obj_id in 0..100 {
+            lease_cache_a.insert(obj_id, obj_id + 1);
+            lease_cache_b.insert(obj_id, obj_id + 1);
+        }
+        for _ in 0..50 {
+            assert_eq!(lease_cache_a.force_evict(), lease_cache_b.force_evict());
+        }
+        assert_eq!(lease_cache_a.get_cache_consumption(), 50);
+    }
+
+    #[test]
+    fn test_force_evict_stress() {
+        let num_objs = 100000;
+        let mut lease_cache = LeaseCache::<usize>::with_rng(7);
+        for obj_id in 0..num_objs {
+            lease_cache.insert(obj_id, obj_id % 1000 + 1);
+        }
+        assert_eq!(lease_cache.get_cache_consumption(), num_objs);
+        let mut evicted = HashSet::new();
+        for i in 1..=num_objs {
+            assert!(evicted.insert(lease_cache.force_evict()));
+            assert_eq!(lease_cache.get_cache_consumption(), num_objs - i);
+        }
+        assert_eq!(lease_cache.live_objects.len(), 0);
+        assert!(lease_cache.expiring_map.is_empty());
+    }
+
+    #[test]
+    fn test_live_objects_consistent() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(7);
+        lease_cache.update(&1, 1);
+        lease_cache.update(&2, 5);
+        lease_cache.update(&3, 5);
+        lease_cache.insert(3, 2);
+        lease_cache.update(&1, 0);
+        lease_cache.remove(&2);
+        lease_cache.advance_time_by(3);
+        lease_cache.update(&4, 2);
+        assert_eq!(lease_cache.live_objects.len(), 1);
+        assert!(lease_cache.live_objects.contains(&4));
+        assert_eq!(lease_cache.force_evict(), 4);
+        assert_eq!(lease_cache.get_cache_consumption(), 0);
+    }
+
+    #[test]
+    fn test_len_and_is_empty() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert!(lease_cache.is_empty());
+        assert_eq!(lease_cache.len(), 0);
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 2);
+        assert!(!lease_cache.is_empty());
+        assert_eq!(lease_cache.len(), 2);
+        assert_eq!(lease_cache.len(), lease_cache.get_cache_consumption());
+        lease_cache.advance_time_by(2);
+        assert!(lease_cache.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.update(&1, 3);
+        lease_cache.update(&2, 5);
+        lease_cache.update(&3, 5);
+        lease_cache.clear();
+        assert!(lease_cache.is_empty());
+        assert_eq!(lease_cache.current_time, 0);
+        assert!(!lease_cache.contains(&1));
+        assert!(lease_cache.expiring_map.is_empty());
+        lease_cache.update(&1, 2);
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(2));
+    }
+
+    #[test]
+    fn test_clear_preserving_time() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.update(&1, 3);
+        lease_cache.update(&2, 5);
+        lease_cache.clear_preserving_time();
+        assert!(lease_cache.is_empty());
+        assert_eq!(lease_cache.current_time, 2);
+    }
+
+    #[test]
+    fn test_cache_access_tracked() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(2);
+        for obj_id in 0..2 {
+            let (result, victims) = lease_cache.cache_access_tracked(TaggedObjectId(10, obj_id));
+            assert_eq!(result, AccessResult::Miss);
+            assert!(victims.is_empty());
+        }
+        let (result, victims) = lease_cache.cache_access_tracked(TaggedObjectId(10, 2));
+        assert_eq!(result, AccessResult::Miss);
+        assert_eq!(victims.len(), 1);
+        assert!(!lease_cache.contains(&victims[0]));
+        assert_eq!(lease_cache.len(), 2);
+    }
+
+    #[test]
+    fn test_hit_miss_counters() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert_eq!(lease_cache.miss_ratio(), 0.0);
+        //a b a b c a with leases that cover every reuse
+        for obj_id in [1, 2, 1, 2, 3, 1] {
+            lease_cache.update(&obj_id, 3);
+        }
+        assert_eq!(lease_cache.hit_count(), 3);
+        assert_eq!(lease_cache.miss_count(), 3);
+        assert_eq!(lease_cache.access_count(), 6);
+        assert_eq!(lease_cache.miss_ratio(), 0.5);
+        lease_cache.reset_stats();
+        assert_eq!(lease_cache.access_count(), 0);
+        assert!(lease_cache.contains(&1));
+        assert_eq!(lease_cache.update(&1, 1), AccessResult::Hit);
+        assert_eq!(lease_cache.miss_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_evict_earliest() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 5);
+        lease_cache.insert(2, 1);
+        lease_cache.insert(3, 100);
+        assert_eq!(lease_cache.evict_earliest(), 2);
+        assert_eq!(lease_cache.evict_earliest(), 1);
+        assert_eq!(lease_cache.evict_earliest(), 3);
+        assert!(lease_cache.is_empty());
+    }
+
+    #[test]
+    fn test_evict_earliest_skips_overdue() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 2);
+        lease_cache.insert(2, 6);
+        lease_cache.current_time = 4;
+        assert_eq!(lease_cache.evict_earliest(), 2);
+        assert_eq!(lease_cache.expired_count(), 1);
+        assert_eq!(lease_cache.capacity_evicted_count(), 1);
+        assert_eq!(lease_cache.try_evict_earliest(), None);
+    }
+
+    #[test]
+    fn test_next_expiration_time() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert_eq!(lease_cache.next_expiration_time(), None);
+        lease_cache.insert(1, 7);
+        lease_cache.insert(2, 3);
+        lease_cache.insert(3, 3);
+        assert_eq!(lease_cache.next_expiration_time(), Some(3));
+        lease_cache.remove(&2);
+        lease_cache.remove(&3);
+        assert_eq!(lease_cache.next_expiration_time(), Some(7));
+        assert_eq!(lease_cache.expiring_map.len(), 1);
+    }
+
+    #[test]
+    fn test_advance_time_by_large_gap() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 1 << 40);
+        lease_cache.insert(2, 1 << 50);
+        assert_eq!(lease_cache.advance_time_by(1 << 45), HashSet::from([1]));
+        assert_eq!(
+            lease_cache.time_until_eviction(&2),
+            Some((1 << 50) - (1 << 45))
+        );
+    }
+
+    #[test]
+    fn test_peek_next_expiring() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert!(lease_cache.peek_next_expiring().is_none());
+        lease_cache.update(&1, 5);
+        lease_cache.update(&2, 2);
+        let (expiration, bucket) = lease_cache.peek_next_expiring().unwrap();
+        assert_eq!(expiration, 4);
+        assert_eq!(bucket, &HashSet::from([2]));
+        assert_eq!(lease_cache.current_time, 2);
+        assert_eq!(lease_cache.len(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(10);
+        lease_cache.update(&1, 3);
+        lease_cache.update(&2, 5);
+        lease_cache.update(&3, 8);
+        let json = serde_json::to_string(&lease_cache).unwrap();
+        let mut restored: LeaseCache<usize> = serde_json::from_str(&json).unwrap();
+        for obj_id in 1..=4 {
+            assert_eq!(lease_cache.contains(&obj_id), restored.contains(&obj_id));
+            assert_eq!(
+                lease_cache.time_until_eviction(&obj_id),
+                restored.time_until_eviction(&obj_id)
+            );
+        }
+        assert_eq!(restored.cache_size, Some(10));
+        for obj_id in [2, 1, 4, 3] {
+            assert_eq!(
+                lease_cache.cache_access(TaggedObjectId(2, obj_id)),
+                restored.cache_access(TaggedObjectId(2, obj_id))
+            );
+        }
+        assert_eq!(lease_cache.len(), restored.len());
+        assert_eq!(lease_cache.miss_count(), restored.miss_count());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_tagged_object_id() {
+        let access = TaggedObjectId(3usize, "x1".to_string());
+        let json = serde_json::to_string(&access).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TaggedObjectId<usize, String>>(&json).unwrap(),
+            access
+        );
+    }
+
+    #[test]
+    fn test_u64_time() {
+        let mut lease_cache = LeaseCache::<usize, u64>::new();
+        lease_cache.update(&1, 1 << 40);
+        lease_cache.update(&2, 3);
+        assert_eq!(lease_cache.time_until_eviction(&1), Some((1 << 40) - 1));
+        assert_eq!(lease_cache.advance_time_by(5), HashSet::from([2]));
+        assert_eq!(lease_cache.current_time, 7u64);
+        lease_cache.set_capacity(1);
+        assert_eq!(
+            lease_cache.cache_access(TaggedObjectId(10u64, 1)),
+            AccessResult::Hit
+        );
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(10));
+    }
+
+    #[test]
+    fn test_u32_time() {
+        let mut lease_cache = LeaseCache::<String, u32>::new();
+        lease_cache.insert("x1".to_string(), 2);
+        lease_cache.insert("x2".to_string(), 4);
+        assert_eq!(lease_cache.next_expiration_time(), Some(2u32));
+        lease_cache.advance_time_by(2);
+        assert!(!lease_cache.contains(&"x1".to_string()));
+        assert_eq!(lease_cache.time_until_eviction(&"x2".to_string()), Some(2));
+    }
+
+    #[test]
+    fn test_extend_lease() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        assert!(lease_cache.extend_lease(&1, 2));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(5));
+        assert!(!lease_cache.expiring_map.contains_key(&3));
+        assert!(lease_cache.expiring_map[&5].contains(&1));
+        assert!(!lease_cache.extend_lease(&2, 2));
+        assert!(!lease_cache.contains(&2));
+        assert!(lease_cache.advance_time_by(4).is_empty());
+        assert_eq!(lease_cache.advance_time(), HashSet::from([1]));
+    }
+
+    #[test]
+    fn test_update_at_stale_timestamp() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert_eq!(lease_cache.update_at(&1, 2, 5), AccessResult::Miss);
+        assert_eq!(lease_cache.update_at(&1, 2, 7), AccessResult::Hit);
+        lease_cache.current_time = 9;
+        //the lease ended at 9, so a repeated or earlier timestamp must miss
+        assert_eq!(lease_cache.update_at(&1, 2, 9), AccessResult::Miss);
+        lease_cache.current_time = 11;
+        assert_eq!(lease_cache.update_at(&1, 2, 4), AccessResult::Miss);
+        assert_eq!(lease_cache.current_time(), 11);
+        assert_eq!(lease_cache.get_expiration(&1), Some(13));
+    }
+
+    #[test]
+    fn test_extend_ended_lease() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.current_time = 3;
+        assert!(!lease_cache.extend_lease(&1, 5));
+        assert!(!lease_cache.contains(&1));
+        assert_eq!(lease_cache.content_map[&1], 3);
+    }
+
+    #[test]
+    fn test_get_expiration() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert_eq!(lease_cache.current_time(), 0);
+        lease_cache.insert(1, 10);
+        assert_eq!(lease_cache.get_expiration(&1), Some(10));
+        assert_eq!(lease_cache.get_expiration(&2), None);
+        lease_cache.advance_time_by(4);
+        assert_eq!(lease_cache.current_time(), 4);
+        assert_eq!(lease_cache.get_expiration(&1), Some(10));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(6));
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_many((0..1000).map(|obj_id| (obj_id, obj_id % 10 + 1)));
+        assert_eq!(lease_cache.len(), 1000);
+        assert_eq!(lease_cache.expiring_map.len(), 10);
+        assert_eq!(lease_cache.time_until_eviction(&123), Some(4));
+        assert_eq!(lease_cache.advance_time().len(), 100);
+    }
+
+    #[test]
+    fn test_insert_many_repeated_object() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 7);
+        lease_cache.insert_many([(1, 2), (2, 2), (1, 5)]);
+        assert_eq!(lease_cache.len(), 2);
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(5));
+        assert_eq!(
+            lease_cache.expiring_map,
+            BTreeMap::from([(2, HashSet::from([2])), (5, HashSet::from([1]))])
+        );
+    }
+
+    #[test]
+    fn test_occupancy_sampling() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.enable_occupancy_sampling(2);
+        for obj_id in 0..4 {
+            lease_cache.insert(obj_id, 2 * obj_id + 1);
+        }
+        for _ in 0..10 {
+            lease_cache.advance_time();
+        }
+        assert_eq!(
+            lease_cache.occupancy_samples(),
+            &[(2, 3), (4, 2), (6, 1), (8, 0), (10, 0)]
+        );
+    }
+
+    #[test]
+    fn test_lease_histogram() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 1);
+        lease_cache.insert(3, 2);
+        lease_cache.insert(4, 3);
+        assert_eq!(
+            lease_cache.lease_histogram(),
+            BTreeMap::from([(1, 2), (2, 1), (3, 1)])
+        );
+        lease_cache.advance_time();
+        assert_eq!(
+            lease_cache.lease_histogram(),
+            BTreeMap::from([(1, 1), (2, 1)])
+        );
+    }
+
+    #[test]
+    fn test_on_evict_reasons() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let evictions = Rc::new(RefCell::new(Vec::new()));
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let log = Rc::clone(&evictions);
+        lease_cache.set_on_evict(move |obj_id, reason| log.borrow_mut().push((*obj_id, reason)));
+        lease_cache.insert(1, 1);
+        lease_cache.insert(2, 1);
+        lease_cache.insert(3, 5);
+        lease_cache.insert(4, 5);
+        lease_cache.advance_time();
+        let mut expired = evictions.borrow_mut().split_off(0);
+        expired.sort_by_key(|(obj_id, _)| *obj_id);
+        assert_eq!(
+            expired,
+            vec![(1, EvictReason::Expired), (2, EvictReason::Expired)]
+        );
+        lease_cache.remove(&3);
+        let victim = lease_cache.force_evict();
+        assert_eq!(
+            *evictions.borrow(),
+            vec![(3, EvictReason::Manual), (victim, EvictReason::Capacity)]
+        );
+    }
+
+    #[test]
+    fn test_on_evict_capacity_in_cache_access() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let capacity_evictions = Rc::new(Cell::new(0));
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(2);
+        let count = Rc::clone(&capacity_evictions);
+        lease_cache.set_on_evict(move |_, reason| {
+            if reason == EvictReason::Capacity {
+                count.set(count.get() + 1);
+            }
+        });
+        for obj_id in 0..5 {
+            lease_cache.cache_access(TaggedObjectId(10, obj_id));
+        }
+        assert_eq!(capacity_evictions.get(), 3);
+        assert!(lease_cache.clone().on_evict.is_none());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        for obj_id in 1..=5 {
+            lease_cache.insert(obj_id, obj_id + 1);
+        }
+        lease_cache.advance_time();
+        let contents: HashMap<usize, usize> = lease_cache
+            .iter()
+            .map(|(obj_id, remaining)| (*obj_id, remaining))
+            .collect();
+        assert_eq!(contents, (1..=5).map(|obj_id| (obj_id, obj_id)).collect());
+        let objects: HashSet<usize> = lease_cache.objects().copied().collect();
+        assert_eq!(objects, (1..=5).collect());
+    }
+
+    #[test]
+    fn test_advance_time_to() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 2);
+        lease_cache.insert(2, 4);
+        lease_cache.insert(3, 6);
+        assert_eq!(lease_cache.advance_time_to(5), HashSet::from([1, 2]));
+        assert_eq!(lease_cache.current_time(), 5);
+        assert!(lease_cache.advance_time_to(5).is_empty());
+        assert!(lease_cache.advance_time_to(3).is_empty());
+        assert_eq!(lease_cache.current_time(), 5);
+        assert_eq!(lease_cache.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_many() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        for obj_id in 0..5 {
+            lease_cache.insert(obj_id, 3);
+        }
+        assert_eq!(lease_cache.remove_many([0, 2, 4]), 3);
+        assert_eq!(lease_cache.remove_many([0, 1, 9]), 1);
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(lease_cache.live_objects.len(), 1);
+        assert_eq!(
+            lease_cache.expiring_map,
+            BTreeMap::from([(3, HashSet::from([3]))])
+        );
+    }
+
+    #[test]
+    fn test_set_capacity_now() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(0);
+        for obj_id in 0..5 {
+            lease_cache.insert(obj_id, 10);
+        }
+        let victims = lease_cache.set_capacity_now(2);
+        assert_eq!(victims.len(), 3);
+        assert_eq!(lease_cache.len(), 2);
+        assert!(victims.iter().all(|obj_id| !lease_cache.contains(obj_id)));
+        assert!(lease_cache.set_capacity_now(4).is_empty());
+    }
+
+    #[test]
+    fn test_estimated_memory_bytes() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let empty = lease_cache.estimated_memory_bytes();
+        lease_cache.insert_many((0..10).map(|obj_id| (obj_id, 5)));
+        let small = lease_cache.estimated_memory_bytes();
+        lease_cache.insert_many((10..1000).map(|obj_id| (obj_id, obj_id)));
+        let large = lease_cache.estimated_memory_bytes();
+        assert!(empty < small && small < large);
+        lease_cache.clear();
+        assert_eq!(lease_cache.estimated_memory_bytes(), empty);
+    }
+
+    #[test]
+    fn test_estimated_memory_bytes_counts_tracking() {
+        let mut untracked = LeaseCache::<usize>::new();
+        let mut tracked = LeaseCache::<usize>::new();
+        tracked.set_track_frequency(true);
+        tracked.enable_tick_log();
+        for lease_cache in [&mut untracked, &mut tracked] {
+            for obj_id in 0..100 {
+                lease_cache.update(&obj_id, 50);
+            }
+        }
+        assert_eq!(tracked.len(), untracked.len());
+        assert!(tracked.estimated_memory_bytes() > untracked.estimated_memory_bytes());
+    }
+
+    #[test]
+    fn test_bucket_counts() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert_eq!(lease_cache.expiration_bucket_count(), 0);
+        assert_eq!(lease_cache.max_bucket_size(), 0);
+        lease_cache.insert_many([(1, 2), (2, 2), (3, 2), (4, 5), (5, 7)]);
+        assert_eq!(lease_cache.expiration_bucket_count(), 3);
+        assert_eq!(lease_cache.max_bucket_size(), 3);
+        lease_cache.advance_time_by(2);
+        assert_eq!(lease_cache.expiration_bucket_count(), 2);
+        assert_eq!(lease_cache.max_bucket_size(), 1);
+    }
+
+    #[test]
+    fn test_stale_expiration_is_drained() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.advance_time_by(10);
+        //schedule 1 behind the clock, which no exact-tick lookup would reach
+        lease_cache.content_map.insert(1, 3);
+        lease_cache.live_objects.insert(1);
+        lease_cache.expiring_map.insert(3, HashSet::from([1]));
+        //and 2 at the current tick
+        lease_cache.content_map.insert(2, 10);
+        lease_cache.live_objects.insert(2);
+        lease_cache.expiring_map.insert(10, HashSet::from([2]));
+        lease_cache.stored_size = 2;
+        lease_cache.insert(3, 2);
+        assert_eq!(lease_cache.advance_time(), HashSet::from([1, 2]));
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(lease_cache.next_expiration_time(), Some(12));
+    }
+
+    #[test]
+    fn test_is_live() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 5);
+        lease_cache.insert_pinned(2);
+        lease_cache.advance_time_by(4);
+        assert!(lease_cache.is_live(&1));
+        assert!(lease_cache.is_live(&2));
+        assert!(!lease_cache.is_live(&3));
+        //leave 1 scheduled behind the clock, as if its bucket was never drained
+        lease_cache.current_time = 7;
+        assert!(lease_cache.content_map.contains_key(&1));
+        assert!(!lease_cache.is_live(&1));
+        assert_eq!(lease_cache.current_time(), 7);
+        assert_eq!(lease_cache.expiration_bucket_count(), 1);
+    }
+
+    #[test]
+    fn test_overdue_objects_not_counted() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.insert(2, 3);
+        lease_cache.insert(3, 20);
+        lease_cache.insert_pinned(4);
+        //move the clock past the bucket of 1 and 2 without draining it
+        lease_cache.current_time = 10;
+        assert_eq!(lease_cache.len(), 2);
+        assert_eq!(lease_cache.get_cache_consumption(), 2);
+        assert!(!lease_cache.contains(&1));
+        assert_eq!(lease_cache.get_expiration(&2), None);
+        assert_eq!(lease_cache.time_until_eviction(&2), None);
+        assert_eq!(lease_cache.iter().collect::<Vec<_>>(), vec![(&3, 10)]);
+        assert_eq!(lease_cache.objects().count(), 2);
+        //an access to an overdue object misses, and the stale bucket is drained
+        let expired = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&expired);
+        lease_cache.set_on_evict(move |obj_id, reason| log.borrow_mut().push((*obj_id, reason)));
+        assert_eq!(lease_cache.update_no_tick(&1, 5), AccessResult::Miss);
+        assert_eq!(lease_cache.len(), 3);
+        let mut expired = expired.borrow().clone();
+        expired.sort_by_key(|&(obj_id, _)| obj_id);
+        assert_eq!(
+            expired,
+            vec![(1, EvictReason::Expired), (2, EvictReason::Expired)]
+        );
+        assert!(lease_cache.advance_time().is_empty());
+        assert_eq!(lease_cache.len(), 3);
+    }
+
+    #[test]
+    fn test_force_evict_skips_overdue() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(7);
+        for obj_id in 0..10 {
+            lease_cache.insert(obj_id, 3);
+        }
+        lease_cache.insert(10, 20);
+        lease_cache.current_time = 5;
+        assert_eq!(lease_cache.force_evict(), 10);
+        assert_eq!(lease_cache.expired_count(), 10);
+        assert_eq!(lease_cache.try_force_evict(), None);
+    }
+
+    #[test]
+    fn test_capacity_getters() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_many((0..3).map(|obj_id| (obj_id, 5)));
+        assert_eq!(lease_cache.capacity(), None);
+        assert!(!lease_cache.is_bounded());
+        assert_eq!(lease_cache.remaining_capacity(), None);
+        lease_cache.set_capacity(5);
+        assert_eq!(lease_cache.capacity(), Some(5));
+        assert!(lease_cache.is_bounded());
+        assert_eq!(lease_cache.remaining_capacity(), Some(2));
+        //not enforced until the next access, so the cache is over capacity
+        lease_cache.set_capacity(1);
+        assert_eq!(lease_cache.remaining_capacity(), Some(0));
+    }
+
+    #[test]
+    fn test_hotspots() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_hotspot_threshold(100);
+        lease_cache.insert_many((0..1000).map(|obj_id| (obj_id, 5)));
+        lease_cache.insert_many((1000..1050).map(|obj_id| (obj_id, 6)));
+        lease_cache.advance_time_by(10);
+        assert_eq!(lease_cache.hotspots(), &[(5, 1000)]);
+        lease_cache.reset_stats();
+        assert!(lease_cache.hotspots().is_empty());
+    }
+
+    #[test]
+    fn test_clock_mode_per_access() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::PerAccess);
+        assert_eq!(lease_cache.clock_mode(), ClockMode::PerAccess);
+        lease_cache.cache_access(TaggedObjectId(2, 1));
+        lease_cache.cache_access(TaggedObjectId(2, 2));
+        lease_cache.cache_access(TaggedObjectId(2, 3));
+        assert_eq!(lease_cache.current_time(), 3);
+        //1 was leased for the two accesses after it
+        assert!(!lease_cache.contains(&1));
+    }
+
+    #[test]
+    fn test_clock_mode_manual() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        for obj_id in 0..10 {
+            lease_cache.cache_access(TaggedObjectId(2, obj_id));
+        }
+        assert_eq!(lease_cache.current_time(), 0);
+        assert_eq!(lease_cache.len(), 10);
+        assert_eq!(
+            lease_cache.cache_access(TaggedObjectId(2, 0)),
+            AccessResult::Hit
+        );
+        lease_cache.advance_time_by(2);
+        assert!(lease_cache.is_empty());
+    }
+
+    #[test]
+    fn test_expiration_granularity() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_expiration_granularity(10);
+        assert_eq!(lease_cache.expiration_granularity(), 10);
+        lease_cache.insert(1, 3);
+        lease_cache.insert(2, 7);
+        lease_cache.insert(3, 10);
+        lease_cache.insert(4, 11);
+        assert_eq!(lease_cache.expiration_bucket_count(), 2);
+        assert_eq!(lease_cache.get_expiration(&1), Some(10));
+        assert_eq!(lease_cache.get_expiration(&2), Some(10));
+        assert_eq!(lease_cache.get_expiration(&4), Some(20));
+        //1 outlives its lease of 3 but is gone with its bucket
+        assert!(lease_cache.advance_time_by(9).is_empty());
+        assert_eq!(lease_cache.advance_time(), HashSet::from([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 5);
+        lease_cache.insert(2, 8);
+        lease_cache.insert_pinned(3);
+        lease_cache.advance_time_by(2);
+        let mut drained: Vec<_> = lease_cache.drain().collect();
+        drained.sort();
+        assert_eq!(drained, vec![(1, 3), (2, 6), (3, usize::MAX)]);
+        assert!(lease_cache.is_empty());
+        assert_eq!(lease_cache.expiration_bucket_count(), 0);
+        assert_eq!(lease_cache.current_time(), 2);
+        assert_eq!(lease_cache.drain().count(), 0);
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_many([(1, 4), (2, 4), (3, 9)]);
+        let mut residents: Vec<_> = lease_cache.into_iter().collect();
+        residents.sort();
+        assert_eq!(residents, vec![(1, 4), (2, 4), (3, 9)]);
+    }
+
+    #[test]
+    fn test_eviction_stats() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(0);
+        lease_cache.set_capacity(3);
+        for obj_id in 0..5 {
+            lease_cache.cache_access(TaggedObjectId(100, obj_id));
         }
-        //check that each object was evicted is within a small epsilon
-        let check_obj1 =
-            ((num_obj1_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
-        let check_obj2 =
-            ((num_obj2_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
-        let check_obj3 =
-            ((num_obj3_evicted as f64 / num_iters as f64) - (1.0 / 3.0)).abs() < epsilon;
-        println!(
-            "eviction count: {} {} {}",
-            num_obj1_evicted, num_obj2_evicted, num_obj3_evicted
+        let resident = *lease_cache.objects().next().unwrap();
+        lease_cache.remove(&resident);
+        lease_cache.insert(20, 2);
+        lease_cache.advance_time_by(5);
+        assert_eq!(lease_cache.capacity_evicted_count(), 2);
+        assert_eq!(lease_cache.manually_removed_count(), 1);
+        assert_eq!(lease_cache.expired_count(), 1);
+        assert_eq!(
+            lease_cache.eviction_stats(),
+            EvictionStats {
+                expired: 1,
+                capacity: 2,
+                manual: 1,
+            }
         );
-        println!(
-            "eviction ratio: {} {} {}",
-            num_obj1_evicted as f64 / num_iters as f64,
-            num_obj2_evicted as f64 / num_iters as f64,
-            num_obj3_evicted as f64 / num_iters as f64
+        lease_cache.reset_stats();
+        assert_eq!(lease_cache.eviction_stats(), EvictionStats::default());
+    }
+
+    #[test]
+    fn test_hit_at_expiry_tick_is_not_expired() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let evictions = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&evictions);
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_on_evict(move |obj_id, reason| log.borrow_mut().push((*obj_id, reason)));
+        assert_eq!(lease_cache.update(&1, 2), AccessResult::Miss);
+        lease_cache.advance_time();
+        assert_eq!(lease_cache.current_time(), 2);
+        assert_eq!(lease_cache.update(&1, 2), AccessResult::Hit);
+        assert_eq!(lease_cache.expired_count(), 0);
+        assert!(evictions.borrow().is_empty());
+        assert_eq!(lease_cache.get_expiration(&1), Some(5));
+        assert_eq!(lease_cache.update_at(&1, 2, 5), AccessResult::Hit);
+        assert_eq!(lease_cache.expired_count(), 0);
+        assert_eq!(lease_cache.get_expiration(&1), Some(7));
+    }
+
+    #[test]
+    fn test_insert_sized() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_eviction_policy(EarliestExpiry);
+        lease_cache.insert_sized(1, 2, 3);
+        lease_cache.insert_sized(2, 9, 5);
+        assert_eq!(lease_cache.total_size(), 8);
+        assert_eq!(lease_cache.get_cache_consumption(), 8);
+        assert_eq!(lease_cache.len(), 2);
+        assert_eq!(lease_cache.set_capacity_now(6), vec![1]);
+        assert_eq!(lease_cache.total_size(), 5);
+        assert_eq!(lease_cache.remaining_capacity(), Some(1));
+        //renewing keeps the size, resizing replaces it
+        lease_cache.insert(2, 9);
+        assert_eq!(lease_cache.size(&2), Some(5));
+        lease_cache.insert_sized(2, 9, 4);
+        assert_eq!(lease_cache.total_size(), 4);
+        lease_cache.advance_time_by(9);
+        assert_eq!(lease_cache.total_size(), 0);
+        assert_eq!(lease_cache.size(&2), None);
+    }
+
+    #[test]
+    fn test_hit_at_expiry_tick_keeps_size() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_sized(1, 2, 5);
+        lease_cache.advance_time();
+        assert_eq!(lease_cache.update(&1, 2), AccessResult::Hit);
+        assert_eq!(lease_cache.size(&1), Some(5));
+        assert_eq!(lease_cache.total_size(), 5);
+    }
+
+    #[test]
+    fn test_contains_unexpired() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.insert(2, 8);
+        //move the clock past the bucket of 1 without draining it
+        lease_cache.current_time = 5;
+        assert_eq!(lease_cache.content_map.len(), 2);
+        assert!(!lease_cache.contains_unexpired(&1));
+        assert!(lease_cache.contains_unexpired(&2));
+        assert_eq!(lease_cache.content_map.len(), 1);
+        assert_eq!(lease_cache.live_objects.len(), 1);
+        assert_eq!(lease_cache.expired_count(), 1);
+        assert_eq!(lease_cache.current_time(), 5);
+    }
+
+    #[test]
+    fn test_force_evict_n() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(4);
+        lease_cache.insert_many((0..100).map(|obj_id| (obj_id, 10)));
+        let mut one_by_one = lease_cache.clone();
+        let victims = lease_cache.force_evict_n(50);
+        assert_eq!(victims.len(), 50);
+        assert_eq!(lease_cache.len(), 50);
+        assert_eq!(
+            victims,
+            (0..50)
+                .map(|_| one_by_one.force_evict())
+                .collect::<Vec<_>>()
         );
-        assert!(check_obj1 && check_obj2 && check_obj3);
+        assert!(victims.iter().all(|obj_id| !lease_cache.contains(obj_id)));
+        //asking for more than is cached empties the cache
+        assert_eq!(lease_cache.force_evict_n(80).len(), 50);
+        assert!(lease_cache.is_empty());
     }
 
     #[test]
-    fn test_remove_from_cache() {
+    fn test_eviction_batch_size() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(4);
+        lease_cache.insert_many((0..100).map(|obj_id| (obj_id, 10)));
+        lease_cache.set_eviction_batch_size(Some(8));
+        //25 over capacity takes four batches of 8
+        assert_eq!(lease_cache.set_capacity_now(75).len(), 32);
+        assert_eq!(lease_cache.len(), 68);
+        lease_cache.set_eviction_batch_size(None);
+        assert_eq!(lease_cache.set_capacity_now(60).len(), 8);
+        assert_eq!(lease_cache.len(), 60);
+    }
+
+    #[test]
+    fn test_cache_access_detailed() {
         let mut lease_cache = LeaseCache::<usize>::new();
-        lease_cache.insert(1, 1);
-        lease_cache.insert(2, 2);
-        lease_cache.insert(3, 3);
-        // lease_cache.cache_consumption = 3;
-        lease_cache.remove(&1);
-        assert!(!lease_cache.content_map.contains_key(&1));
-        assert_eq!(lease_cache.get_cache_consumption(), 2);
-        lease_cache.remove(&2);
+        lease_cache.set_eviction_policy(EarliestExpiry);
+        lease_cache.set_capacity(2);
+        let outcome = lease_cache.cache_access_detailed(TaggedObjectId(5, 1));
+        assert!(outcome.was_insertion && outcome.evicted.is_empty());
+        lease_cache.cache_access_detailed(TaggedObjectId(9, 2));
+        let outcome = lease_cache.cache_access_detailed(TaggedObjectId(9, 3));
+        assert_eq!(outcome.result, AccessResult::Miss);
+        assert!(outcome.was_insertion);
+        assert_eq!(outcome.evicted, vec![1]);
+        let outcome = lease_cache.cache_access_detailed(TaggedObjectId(9, 3));
+        assert_eq!(outcome.result, AccessResult::Hit);
+        assert!(!outcome.was_insertion && outcome.evicted.is_empty());
+        let outcome = lease_cache.cache_access_detailed(TaggedObjectId(0, 4));
+        assert!(!outcome.was_insertion);
+    }
+
+    #[test]
+    fn test_insert_lease_saturates() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.advance_time_by(5);
+        lease_cache.insert(1, usize::MAX);
+        assert_eq!(lease_cache.get_expiration(&1), Some(usize::MAX));
+        assert!(lease_cache.extend_lease(&1, 10));
+        assert_eq!(lease_cache.get_expiration(&1), Some(usize::MAX));
+        assert!(lease_cache.advance_time_by(1000).is_empty());
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(usize::MAX - 1005));
+    }
+
+    #[test]
+    fn test_age_all() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.insert(2, 5);
+        assert!(lease_cache.age_all(2).is_empty());
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(3));
+        assert_eq!(lease_cache.age_all(1), HashSet::from([1]));
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(
+            lease_cache.expiring_map,
+            BTreeMap::from([(2, HashSet::from([2]))])
+        );
+        assert_eq!(lease_cache.current_time(), 0);
+    }
+
+    #[test]
+    fn test_age_all_coarsens() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_expiration_granularity(4);
+        lease_cache.insert(1, 8);
+        lease_cache.insert(2, 12);
+        assert!(lease_cache.age_all(3).is_empty());
+        assert_eq!(
+            lease_cache.expiring_map,
+            BTreeMap::from([(8, HashSet::from([1])), (12, HashSet::from([2]))])
+        );
+        assert_eq!(lease_cache.get_expiration(&2), Some(12));
+    }
+
+    #[test]
+    fn test_renew() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.advance_time_by(2);
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
+        assert!(lease_cache.renew(&1, 4));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(4));
+        assert_eq!(lease_cache.current_time(), 2);
+        assert!(lease_cache.renew(&1, 1));
+        assert_eq!(
+            lease_cache.expiring_map,
+            BTreeMap::from([(3, HashSet::from([1]))])
+        );
+        assert!(!lease_cache.renew(&2, 4));
+        assert!(!lease_cache.contains(&2));
+    }
+
+    #[test]
+    fn test_renew_ended_or_zero_lease() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.insert(2, 5);
+        lease_cache.current_time = 3;
+        assert!(!lease_cache.renew(&1, 4));
+        assert!(!lease_cache.contains(&1));
+        assert!(lease_cache.renew(&2, 0));
+        assert!(!lease_cache.contains(&2));
         assert!(!lease_cache.content_map.contains_key(&2));
-        assert_eq!(lease_cache.get_cache_consumption(), 1);
-        lease_cache.remove(&3);
-        assert!(!lease_cache.content_map.contains_key(&3));
-        assert_eq!(lease_cache.get_cache_consumption(), 0);
+        assert_eq!(lease_cache.stored_len(), 1);
     }
 
     #[test]
-    fn test_lease_one() {
+    fn test_update_no_tick() {
         let mut lease_cache = LeaseCache::<usize>::new();
-        let obj_id = 1;
-        let access_result= lease_cache.update(&obj_id, 1);
-        assert_eq!(lease_cache.time_until_eviction(&obj_id), Some(1));
+        assert!(matches!(
+            lease_cache.update_no_tick(&1, 2),
+            AccessResult::Miss
+        ));
+        assert!(matches!(
+            lease_cache.update_no_tick(&1, 2),
+            AccessResult::Hit
+        ));
+        assert!(matches!(
+            lease_cache.update_no_tick(&2, 0),
+            AccessResult::Miss
+        ));
+        assert!(matches!(
+            lease_cache.update_no_tick(&1, 0),
+            AccessResult::Hit
+        ));
+        assert_eq!(lease_cache.current_time(), 0);
+        assert_eq!(lease_cache.access_count(), 4);
+        assert!(lease_cache.is_empty());
+
+        lease_cache.update_no_tick(&3, 2);
+        lease_cache.advance_time_by(2);
+        assert!(matches!(
+            lease_cache.update_no_tick(&3, 2),
+            AccessResult::Miss
+        ));
+        assert_eq!(lease_cache.current_time(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.update(&1, 5);
+        lease_cache.update(&2, 3);
+        lease_cache.update(&1, 4);
+        let snapshot = lease_cache.snapshot();
+        assert_eq!(snapshot.len(), 2);
+
         lease_cache.advance_time();
-        println!("{:?}", lease_cache.time_until_eviction(&obj_id));
-        assert!(!lease_cache.contains(&obj_id));
+        lease_cache.enable_second_chance(10, 5);
+        lease_cache.update(&3, 2);
+        lease_cache.insert_many((10..20).map(|obj_id| (obj_id, 9)));
+        lease_cache.remove(&1);
+        lease_cache.force_evict();
+        lease_cache.restore(&snapshot);
+
+        assert_eq!(lease_cache.current_time(), snapshot.current_time());
+        assert_eq!(lease_cache.expiring_map, snapshot.expiring_map);
+        assert_eq!(lease_cache.content_map, snapshot.content_map);
+        assert_eq!(lease_cache.live_objects.len(), 2);
+        assert_eq!((lease_cache.hit_count(), lease_cache.miss_count()), (1, 2));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(4));
+        assert!(lease_cache.last_access.is_empty());
+        assert_eq!(lease_cache.last_evicted(), None);
+        assert_eq!(lease_cache.peak_occupancy(), 2);
     }
 
     #[test]
-    fn test_lease_cache_update_lease_one() {
+    fn test_warmup() {
         let mut lease_cache = LeaseCache::<usize>::new();
-        let obj_id = 1;
-        let mut access_result = lease_cache.update(&obj_id, 1);
-        assert_eq!(access_result, AccessResult::Miss);
-        assert_eq!(lease_cache.time_until_eviction(&obj_id), Some(1));
-        assert!(lease_cache.contains(&obj_id));
-        access_result = lease_cache.update(&obj_id, 1);
-        assert_eq!(access_result, AccessResult::Hit);
-        assert_eq!(lease_cache.time_until_eviction(&obj_id), Some(1));
-        assert!(lease_cache.contains(&obj_id));
-        access_result = lease_cache.update(&2, 2);
-        assert_eq!(access_result, AccessResult::Miss);
-        assert_eq!(lease_cache.contains(&obj_id), false);
+        lease_cache.warmup(3);
+        for obj_id in 0..3 {
+            lease_cache.update(&obj_id, 10);
+        }
+        assert_eq!(lease_cache.access_count(), 0);
+        assert_eq!(lease_cache.len(), 3);
+        for obj_id in 0..4 {
+            lease_cache.update(&obj_id, 10);
+        }
+        assert_eq!(lease_cache.hit_count(), 3);
+        assert_eq!(lease_cache.miss_count(), 1);
+    }
 
+    #[test]
+    fn test_evict_earliest_tie_break() {
+        for _ in 0..20 {
+            //every run hashes differently, the victims must not change
+            let mut lease_cache = LeaseCache::<String>::new();
+            for obj_id in ["c", "a", "b", "d"] {
+                lease_cache.insert(obj_id.to_string(), 2);
+            }
+            lease_cache.insert("e".to_string(), 1);
+            assert_eq!(lease_cache.evict_earliest(), "e");
+            assert_eq!(lease_cache.evict_earliest(), "c");
+            lease_cache.remove(&"a".to_string());
+            //d now sits in a's old slot, ahead of b
+            assert_eq!(lease_cache.evict_earliest(), "d");
+            assert_eq!(lease_cache.evict_earliest(), "b");
+        }
     }
 
-    // #[test]
-    // fn test_lease_zero() {
-    //     let mut lease_cache = LeaseCache::<usize>::new();
-    //     lease_cache.update(&1, 2);
-    //     lease_cache.update(&2, 0);
-    //     assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
-    //     assert_eq!(lease_cache.time_until_eviction(&2), None);
-    //     assert!(!lease_cache.content_map.contains_key(&2));
-    // }
+    #[test]
+    fn test_last_evicted() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(2);
+        lease_cache.cache_access(TaggedObjectId(10, 1));
+        lease_cache.cache_access(TaggedObjectId(10, 2));
+        assert_eq!(lease_cache.last_evicted(), None);
+        let (_, victims) = lease_cache.cache_access_tracked(TaggedObjectId(10, 3));
+        assert_eq!(victims.len(), 1);
+        assert_eq!(lease_cache.last_evicted(), victims.first());
+        assert!(!lease_cache.contains(&victims[0]));
+    }
 
     #[test]
-    fn test_wrap_around_lease_one() {
+    fn test_insert_pinned() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_pinned(1);
+        lease_cache.insert(2, 5);
+        lease_cache.insert(3, 5);
+        lease_cache.insert_pinned(3);
+        assert_eq!(lease_cache.advance_time_by(1_000_000), HashSet::from([2]));
+        assert!(lease_cache.contains(&1) && lease_cache.contains(&3));
+        assert_eq!(lease_cache.len(), 2);
+        assert_eq!(lease_cache.time_until_eviction(&1), None);
+        assert!(lease_cache.expiring_map.is_empty());
+        assert!(matches!(lease_cache.update(&1, 4), AccessResult::Hit));
+        assert!(lease_cache.is_pinned(&1));
+        assert!(!lease_cache.renew(&1, 4));
+
+        //pinned objects still give way under capacity pressure
+        let victim = lease_cache.force_evict();
+        assert!(!lease_cache.contains(&victim));
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(lease_cache.live_objects.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_unpins() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_pinned(1);
+        lease_cache.insert(1, 2);
+        assert!(!lease_cache.is_pinned(&1));
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(lease_cache.advance_time_by(2), HashSet::from([1]));
+        assert!(lease_cache.is_empty());
+        assert_eq!(lease_cache.live_objects.len(), 0);
+    }
+
+    #[test]
+    fn test_expiration_schedule() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_many([(1, 3), (2, 1), (3, 3), (4, 7)]);
+        lease_cache.insert_pinned(5);
+        let mut schedule = lease_cache.expiration_schedule();
+        schedule.values_mut().for_each(|bucket| bucket.sort());
+        assert_eq!(
+            schedule,
+            BTreeMap::from([(1, vec![2]), (3, vec![1, 3]), (7, vec![4])])
+        );
+        //the copy is detached from the cache
+        schedule.clear();
+        assert_eq!(lease_cache.expiration_bucket_count(), 3);
+    }
+
+    #[test]
+    fn test_zero_lease_insert_matches_update() {
+        let mut inserted = LeaseCache::<usize>::new();
+        let mut updated = LeaseCache::<usize>::new();
+        inserted.insert(1, 0);
+        updated.update_no_tick(&1, 0);
+        assert!(!inserted.contains(&1) && !updated.contains(&1));
+
+        inserted.insert(1, 3);
+        updated.update_no_tick(&1, 3);
+        inserted.insert(1, 0);
+        updated.update_no_tick(&1, 0);
+        for lease_cache in [&inserted, &updated] {
+            assert!(lease_cache.is_empty());
+            assert!(lease_cache.expiring_map.is_empty());
+            assert_eq!(lease_cache.live_objects.len(), 0);
+        }
+
+        inserted.insert_many([(2, 4), (3, 4), (2, 0)]);
+        assert!(!inserted.contains(&2));
+        assert_eq!(
+            inserted.expiring_map,
+            BTreeMap::from([(4, HashSet::from([3]))])
+        );
+    }
+
+    #[test]
+    fn test_zero_lease_keeps_pinned() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_pinned(1);
+        lease_cache.insert_pinned(2);
+        lease_cache.insert(1, 0);
+        lease_cache.insert_many([(2, 0)]);
+        assert!(lease_cache.contains(&1) && lease_cache.contains(&2));
+        assert_eq!(lease_cache.pinned_count(), 2);
+        assert_eq!(lease_cache.expired_count(), 0);
+    }
+
+    #[test]
+    fn test_average_occupancy() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        assert_eq!(lease_cache.average_occupancy(), 0.0);
+        lease_cache.insert_many([(1, 10), (2, 10)]);
+        lease_cache.advance_time_by(3);
+        lease_cache.insert_many([(3, 10), (4, 10)]);
+        lease_cache.advance_time();
+        assert_eq!(lease_cache.average_occupancy(), 2.5);
+    }
+
+    #[test]
+    fn test_average_occupancy_across_expirations() {
+        //one jump over two expirations: 3 objects for 2 ticks, 2 for 3, 1 for 5
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert_many([(1, 2), (2, 5), (3, 100)]);
+        lease_cache.advance_time_by(10);
+        assert_eq!(lease_cache.average_occupancy(), (6 + 6 + 5) as f64 / 10.0);
+        lease_cache.reset_stats();
+        assert_eq!(lease_cache.average_occupancy(), 0.0);
+    }
+
+    #[test]
+    fn test_custom_hasher_matches_default() {
+        type FixedHasher = std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+        let mut default_cache = LeaseCache::<usize>::with_rng(3);
+        let mut fixed_cache =
+            LeaseCache::<usize, usize, FixedHasher>::with_rng_and_hasher(3, FixedHasher::default());
+        default_cache.set_capacity(4);
+        fixed_cache.set_capacity(4);
+        for i in 0..200 {
+            let access = TaggedObjectId(i % 7, i % 11);
+            assert_eq!(
+                default_cache.cache_access(access.clone()),
+                fixed_cache.cache_access(access)
+            );
+        }
+        assert_eq!(default_cache.hit_count(), fixed_cache.hit_count());
+        assert_eq!(default_cache.len(), fixed_cache.len());
+    }
+
+    #[test]
+    fn test_reset_repeats_evictions() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        fn evictions(lease_cache: &mut LeaseCache<usize>) -> Vec<usize> {
+            let evicted = Rc::new(RefCell::new(Vec::new()));
+            let sink = evicted.clone();
+            lease_cache.set_on_evict(move |obj_id, _| sink.borrow_mut().push(*obj_id));
+            for obj_id in 0..50 {
+                lease_cache.cache_access(TaggedObjectId(100, obj_id));
+            }
+            let evicted = evicted.borrow().clone();
+            evicted
+        }
+        let mut lease_cache = LeaseCache::<usize>::with_rng(5);
+        lease_cache.set_capacity(8);
+        let first = evictions(&mut lease_cache);
+        lease_cache.reset();
+        assert!(lease_cache.is_empty());
+        assert_eq!(lease_cache.current_time(), 0);
+        assert_eq!(lease_cache.hit_count() + lease_cache.miss_count(), 0);
+        assert_eq!(lease_cache.capacity(), Some(8));
+        assert_eq!(evictions(&mut lease_cache), first);
+        assert_eq!(first.len(), 42);
+    }
+
+    #[test]
+    fn test_expiring_within() {
         let mut lease_cache = LeaseCache::<usize>::new();
-        lease_cache.current_time = MAX_EXPIRING_VEC_SIZE - 1;
         lease_cache.insert(1, 1);
+        lease_cache.insert(2, 5);
+        lease_cache.insert(3, 10);
+        lease_cache.insert_pinned(4);
+        assert_eq!(lease_cache.expiring_within(5), vec![&1, &2]);
+        assert_eq!(lease_cache.expiring_within(0), Vec::<&usize>::new());
+        //once 1 is overdue it is no longer reported
+        lease_cache.current_time = 1;
+        assert_eq!(lease_cache.expiring_within(4), vec![&2]);
+        assert_eq!(lease_cache.expiring_within(usize::MAX), vec![&2, &3]);
+    }
+
+    #[test]
+    fn test_try_force_evict_empty() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(0);
+        assert_eq!(lease_cache.try_force_evict(), None);
+        lease_cache.insert(1, 5);
+        assert_eq!(lease_cache.try_force_evict(), Some(1));
+        assert_eq!(lease_cache.try_force_evict(), None);
+        assert_eq!(lease_cache.last_evicted(), Some(&1));
+        assert_eq!(lease_cache.capacity_evicted_count(), 1);
+    }
+
+    #[test]
+    fn test_tick_log() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        lease_cache.insert(1, 2);
         lease_cache.insert(2, 2);
-        lease_cache.insert(3, 3);
-        assert_eq!(lease_cache.time_until_eviction(&1), Some(1));
-        assert_eq!(lease_cache.time_until_eviction(&2), Some(2));
-        assert_eq!(lease_cache.time_until_eviction(&3), Some(3));
+        lease_cache.insert(3, 5);
         lease_cache.advance_time();
-        assert_eq!(lease_cache.time_until_eviction(&1), None);
-        assert_eq!(lease_cache.time_until_eviction(&2), Some(1));
-        assert_eq!(lease_cache.time_until_eviction(&3), Some(2));
+        lease_cache.enable_tick_log();
         lease_cache.advance_time();
-        assert_eq!(lease_cache.time_until_eviction(&1), None);
-        assert_eq!(lease_cache.time_until_eviction(&2), None);
+        lease_cache.advance_time_to(10);
+        //neither moves the clock nor expires anything
+        lease_cache.advance_time_by(0);
+        let mut log = lease_cache.tick_log().to_vec();
+        log[0].evicted.sort();
+        assert_eq!(
+            log,
+            vec![
+                TickRecord {
+                    time: 2,
+                    evicted: vec![1, 2],
+                    occupancy: 1,
+                },
+                TickRecord {
+                    time: 10,
+                    evicted: vec![3],
+                    occupancy: 0,
+                },
+            ]
+        );
+        lease_cache.clear_tick_log();
+        lease_cache.advance_time();
+        assert_eq!(lease_cache.tick_log().len(), 1);
+        lease_cache.disable_tick_log();
+        lease_cache.advance_time();
+        assert!(lease_cache.tick_log().is_empty());
+    }
+
+    #[test]
+    fn test_second_chance() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        lease_cache.enable_second_chance(3, 2);
+        lease_cache.update(&1, 3);
+        lease_cache.update(&2, 3);
+        //2 is kept longer without being accessed, so it goes stale
+        lease_cache.extend_lease(&2, 5);
+        let expired = lease_cache.advance_time_to(3);
+        assert!(expired.is_empty());
+        assert_eq!(lease_cache.get_expiration(&1), Some(5));
+        //the reprieve is used up
+        assert_eq!(lease_cache.advance_time_to(5), HashSet::from([1]));
+        assert_eq!(lease_cache.advance_time_to(8), HashSet::from([2]));
+        assert!(lease_cache.is_empty());
+    }
+
+    #[test]
+    fn test_with_start_time() {
+        let mut lease_cache = LeaseCache::<usize>::with_start_time(100);
+        assert_eq!(lease_cache.current_time(), 100);
+        lease_cache.insert(1, 10);
+        assert_eq!(lease_cache.get_expiration(&1), Some(110));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(10));
+        assert_eq!(lease_cache.advance_time_to(110), HashSet::from([1]));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        lease_cache.insert(1, 10);
+        lease_cache.insert(2, 4);
+        lease_cache.insert(3, 20);
+        lease_cache.advance_time_to(2);
+        let mut other = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        other.advance_time_to(3);
+        other.insert(1, 4);
+        other.insert(2, 6);
+        other.insert(4, 1);
+        other.insert_pinned(5);
+        lease_cache.merge(other);
+        assert_eq!(lease_cache.current_time(), 3);
+        //each shared object keeps its later expiration
+        assert_eq!(lease_cache.get_expiration(&1), Some(10));
+        assert_eq!(lease_cache.get_expiration(&2), Some(9));
+        assert_eq!(lease_cache.get_expiration(&3), Some(20));
+        assert_eq!(lease_cache.get_expiration(&4), Some(4));
+        assert!(lease_cache.is_pinned(&5));
+        assert_eq!(lease_cache.len(), 5);
+        assert_eq!(lease_cache.total_size(), 5);
+    }
+
+    #[test]
+    fn test_merge_overdue_and_granularity() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        lease_cache.set_expiration_granularity(4);
+        lease_cache.insert(1, 2);
+        let mut other = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        other.insert(1, 6);
+        other.insert(2, 1);
+        //2 is overdue in other, with both clocks at 2
+        lease_cache.current_time = 2;
+        other.current_time = 2;
+        lease_cache.merge(other);
+        assert!(!lease_cache.is_stored(&2));
+        assert_eq!(lease_cache.get_expiration(&1), Some(8));
+        assert_eq!(
+            lease_cache.expiring_map,
+            BTreeMap::from([(8, HashSet::from([1]))])
+        );
+    }
+
+    #[test]
+    fn test_premature_eviction_histogram() {
+        let mut lease_cache = LeaseCache::<usize>::with_clock_mode(ClockMode::Manual);
+        lease_cache.insert(1, 8);
+        lease_cache.insert(2, 12);
+        lease_cache.insert_pinned(3);
+        lease_cache.advance_time_by(3);
+        lease_cache.set_eviction_policy(EarliestExpiry);
+        assert_eq!(lease_cache.force_evict(), 1);
+        lease_cache.remove(&2);
+        lease_cache.remove(&3);
+        assert_eq!(
+            *lease_cache.premature_eviction_histogram(),
+            BTreeMap::from([(5, 1), (9, 1)])
+        );
+        lease_cache.reset_stats();
+        assert!(lease_cache.premature_eviction_histogram().is_empty());
+    }
+
+    #[test]
+    fn test_objects_expiring_at() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 4);
+        lease_cache.insert(2, 4);
+        lease_cache.insert(3, 6);
+        assert_eq!(
+            lease_cache.objects_expiring_at(4),
+            Some(&HashSet::from([1, 2]))
+        );
+        assert_eq!(lease_cache.objects_expiring_at(5), None);
+        assert_eq!(lease_cache.expiration_bucket_count(), 2);
+        lease_cache.current_time = 4;
+        assert_eq!(lease_cache.objects_expiring_at(4), None);
+    }
+
+    #[test]
+    fn test_peak_occupancy() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        for obj_id in 0..4 {
+            lease_cache.cache_access(TaggedObjectId(4, obj_id));
+        }
+        assert_eq!(lease_cache.len(), 4);
+        //each new object now replaces one that expires
+        for obj_id in 4..10 {
+            lease_cache.cache_access(TaggedObjectId(1, obj_id));
+        }
+        assert_eq!(lease_cache.len(), 1);
+        assert_eq!(lease_cache.peak_occupancy(), 4);
+        lease_cache.clear();
+        assert_eq!(lease_cache.peak_occupancy(), 0);
+    }
+
+    #[test]
+    fn test_peak_occupancy_bounded() {
+        let mut lease_cache = LeaseCache::<usize>::with_rng(0);
+        lease_cache.set_capacity(3);
+        for obj_id in 0..10 {
+            lease_cache.cache_access(TaggedObjectId(100, obj_id));
+        }
+        assert_eq!(lease_cache.len(), 3);
+        assert_eq!(lease_cache.peak_occupancy(), 3);
+    }
+
+    #[test]
+    fn test_default_leases() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_default_lease(1, 5);
+        lease_cache.cache_access(TaggedObjectId(0, 1));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(5));
+        //objects without a default are still dropped by a lease of 0
+        lease_cache.cache_access(TaggedObjectId(0, 2));
+        assert!(!lease_cache.contains(&2));
+        //a nonzero lease wins over the default
+        lease_cache.cache_access(TaggedObjectId(2, 1));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(2));
+        lease_cache.remove_default_lease(&1);
+        lease_cache.cache_access(TaggedObjectId(0, 1));
+        assert!(!lease_cache.contains(&1));
+    }
+
+    #[test]
+    fn test_force_evict_distribution() {
+        let num_objs = 8;
+        let num_iters = 40_000;
+        let mut lease_cache = LeaseCache::<String>::with_rng(11);
+        assert!(lease_cache.force_evict_distribution().is_none());
+        lease_cache.set_track_force_evictions(true);
+        for _ in 0..num_iters {
+            for obj_id in 0..num_objs {
+                lease_cache.insert(format!("x{}", obj_id), 100);
+            }
+            lease_cache.force_evict();
+        }
+        let distribution = lease_cache.force_evict_distribution().unwrap();
+        assert_eq!(distribution.len(), num_objs);
+        assert_eq!(distribution.values().sum::<u64>(), num_iters);
+        let expected = num_iters as f64 / num_objs as f64;
+        for (obj_id, &count) in distribution {
+            assert!(
+                (count as f64 / expected - 1.0).abs() < 0.05,
+                "{} {}",
+                obj_id,
+                count
+            );
+        }
+        lease_cache.reset_stats();
+        assert!(lease_cache.force_evict_distribution().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_try_insert() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(2);
+        assert_eq!(lease_cache.try_insert(1, 5), Ok(()));
+        assert_eq!(lease_cache.try_insert(2, 5), Ok(()));
+        assert_eq!(lease_cache.try_insert(3, 5), Err(CacheFull));
+        assert_eq!(
+            lease_cache.try_insert(3, 5).unwrap_err().to_string(),
+            "cache is at capacity"
+        );
+        let mut contents: Vec<_> = lease_cache.objects().copied().collect();
+        contents.sort();
+        assert_eq!(contents, vec![1, 2]);
+        assert_eq!(lease_cache.capacity_evicted_count(), 0);
+        //a renewal does not need room
+        assert_eq!(lease_cache.try_insert(1, 9), Ok(()));
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(9));
+        let mut unbounded = LeaseCache::<usize>::new();
+        assert!((0..100).all(|obj_id| unbounded.try_insert(obj_id, 5).is_ok()));
+    }
+
+    #[test]
+    fn test_advance_time_spares_pinned() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.insert(1, 3);
+        lease_cache.insert(2, 3);
+        //pinning drops the lease that would have ended at 3
+        lease_cache.insert_pinned(2);
+        assert_eq!(lease_cache.pinned_count(), 1);
+        assert_eq!(lease_cache.advance_time_by(10), HashSet::from([1]));
+        assert_eq!(lease_cache.advance_time_to(usize::MAX), HashSet::new());
+        assert!(lease_cache.contains(&2));
+        assert_eq!(lease_cache.pinned_count(), 1);
+    }
+
+    #[test]
+    fn test_scale_leases() {
+        let mut lease_cache = LeaseCache::<usize>::with_start_time(10);
+        lease_cache.insert(1, 4);
+        lease_cache.insert(2, 8);
+        lease_cache.insert(3, 1);
+        lease_cache.insert_pinned(4);
+        //1 * 0.5 rounds up to 1, so 3 stays
+        assert!(lease_cache.scale_leases(0.5).is_empty());
+        assert_eq!(lease_cache.time_until_eviction(&1), Some(2));
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(4));
         assert_eq!(lease_cache.time_until_eviction(&3), Some(1));
+        //1 and 3 now share a bucket
+        assert!(lease_cache.scale_leases(0.6).is_empty());
+        assert_eq!(lease_cache.expiration_bucket_count(), 2);
+        assert!(lease_cache.scale_leases(3.0).is_empty());
+        assert_eq!(lease_cache.time_until_eviction(&2), Some(6));
+        assert_eq!(lease_cache.scale_leases(0.0), HashSet::from([1, 2, 3]));
+        assert_eq!(lease_cache.len(), 1);
+        assert!(lease_cache.is_pinned(&4));
+    }
+
+    #[test]
+    fn test_cache_equality() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(4);
+        lease_cache.insert(1, 5);
+        lease_cache.insert(2, 7);
+        lease_cache.insert_pinned(3);
+        let copy = lease_cache.clone();
+        assert!(copy == lease_cache);
+        //the same contents reached another way are equal too
+        let mut rebuilt = LeaseCache::<usize>::new();
+        rebuilt.set_capacity(4);
+        rebuilt.insert_pinned(3);
+        rebuilt.insert(2, 7);
+        rebuilt.insert(1, 9);
+        rebuilt.renew(&1, 5);
+        assert!(rebuilt == lease_cache);
+        let mut mutated = copy.clone();
+        mutated.extend_lease(&1, 1);
+        assert!(mutated != lease_cache);
+        let mut mutated = copy.clone();
+        mutated.advance_time();
+        assert!(mutated != lease_cache);
+        let mut mutated = copy;
+        mutated.set_capacity(5);
+        assert!(mutated != lease_cache);
     }
 }
@@ -0,0 +1,151 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Occupancy, TaggedObjectId};
+
+/// Plain LRU baseline. It accepts the same `TaggedObjectId` accesses as
+/// `LeaseCache` and ignores the lease tag, so both can be replayed on one
+/// trace with [`run_trace`](crate::run_trace).
+#[derive(Debug, Clone)]
+pub struct LruCache<Obj: ObjIdTraits> {
+    //map from ObjId to the logical time of its last access
+    last_access: HashMap<Obj, u64>,
+    //the same entries ordered by last access, oldest first
+    recency: BTreeMap<u64, Obj>,
+    clock: u64,
+    cache_size: Option<usize>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<Obj: ObjIdTraits> LruCache<Obj> {
+    pub fn new() -> Self {
+        LruCache {
+            last_access: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            cache_size: None,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Same as [`CacheSim::set_capacity`], callable without naming the tag
+    /// type.
+    pub fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.cache_size = Some(cache_size);
+        self
+    }
+
+    /// Records an access to `obj_id`, evicting the least recently used
+    /// objects if the cache goes over capacity.
+    pub fn access(&mut self, obj_id: Obj) -> AccessResult {
+        let now = self.clock;
+        self.clock += 1;
+        let result = match self.last_access.insert(obj_id.clone(), now) {
+            Some(previous) => {
+                self.recency.remove(&previous);
+                self.hits += 1;
+                AccessResult::Hit
+            }
+            None => {
+                self.misses += 1;
+                AccessResult::Miss
+            }
+        };
+        self.recency.insert(now, obj_id);
+        if let Some(cache_size) = self.cache_size {
+            while self.last_access.len() > cache_size {
+                let (_, victim) = self.recency.pop_first().unwrap();
+                self.last_access.remove(&victim);
+            }
+        }
+        result
+    }
+
+    pub fn contains(&self, obj_id: &Obj) -> bool {
+        self.last_access.contains_key(obj_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.last_access.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last_access.is_empty()
+    }
+
+    pub fn hit_count(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn miss_count(&self) -> u64 {
+        self.misses
+    }
+}
+
+impl<Obj: ObjIdTraits> Default for LruCache<Obj> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tag: ObjIdTraits, Obj: ObjIdTraits> CacheSim<TaggedObjectId<Tag, Obj>> for LruCache<Obj> {
+    fn cache_access(&mut self, access: TaggedObjectId<Tag, Obj>) -> AccessResult {
+        let TaggedObjectId(_lease, obj_id) = access;
+        self.access(obj_id)
+    }
+
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        LruCache::set_capacity(self, cache_size)
+    }
+}
+
+impl<Obj: ObjIdTraits> Occupancy for LruCache<Obj> {
+    fn occupancy(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{run_trace, LeaseCache};
+
+    #[test]
+    fn test_lru_eviction_order() {
+        let mut lru = LruCache::new();
+        lru.set_capacity(2);
+        lru.access(1);
+        lru.access(2);
+        lru.access(1);
+        lru.access(3);
+        assert!(lru.contains(&1));
+        assert!(!lru.contains(&2));
+        assert!(lru.contains(&3));
+    }
+
+    #[test]
+    fn test_lru_against_lease_cache() {
+        //a cyclic trace one larger than the cache makes LRU miss every time,
+        //while a lease on a alone keeps it resident for its reuse
+        let trace: Vec<TaggedObjectId<usize, usize>> = [1, 2, 3, 1, 2, 3]
+            .into_iter()
+            .map(|obj_id| TaggedObjectId(if obj_id == 1 { 3 } else { 0 }, obj_id))
+            .collect();
+
+        let mut lru = LruCache::new();
+        lru.set_capacity(2);
+        let lru_stats = run_trace(&mut lru, trace.clone());
+        assert_eq!(lru_stats.misses, 6);
+        assert_eq!(lru_stats.final_occupancy, 2);
+
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(2);
+        let lease_stats = run_trace(&mut lease_cache, trace);
+        assert_eq!(lease_stats.misses, 5);
+        assert_eq!(lease_stats.hits, lru.hit_count() + 1);
+    }
+}
@@ -0,0 +1,88 @@
+use abstract_cache::ObjIdTraits;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::TaggedObjectId;
+
+/// Belady's OPT: evicts the object whose next use is furthest in the future.
+/// It needs the whole trace up front, so instead of implementing `CacheSim`
+/// it replays a complete trace in [`OptCache::miss_count`]. The lease tags
+/// are ignored, which lets it run on the same trace as a `LeaseCache`.
+#[derive(Debug, Clone, Default)]
+pub struct OptCache {
+    cache_size: Option<usize>,
+}
+
+impl OptCache {
+    pub fn new() -> Self {
+        OptCache { cache_size: None }
+    }
+
+    pub fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.cache_size = Some(cache_size);
+        self
+    }
+
+    /// The minimum number of misses any cache of this capacity can incur on
+    /// `trace`. Objects may bypass the cache, so an access whose object is
+    /// reused later than everything resident is not cached. Without a
+    /// capacity only first accesses miss.
+    pub fn miss_count<Tag: ObjIdTraits, Obj: ObjIdTraits>(
+        &self,
+        trace: &[TaggedObjectId<Tag, Obj>],
+    ) -> u64 {
+        let len = trace.len();
+        //next_use[i] is the position of the next access to trace[i].1, or a
+        //distinct value >= len if there is none
+        let mut next_use = vec![0; len];
+        let mut seen: HashMap<&Obj, usize> = HashMap::new();
+        for (i, TaggedObjectId(_, obj_id)) in trace.iter().enumerate().rev() {
+            next_use[i] = seen.insert(obj_id, i).unwrap_or(len + i);
+        }
+
+        let mut misses = 0;
+        //resident objects and their next use; next uses are all distinct,
+        //so they also key by_next_use
+        let mut resident: HashMap<&Obj, usize> = HashMap::new();
+        let mut by_next_use: BTreeMap<usize, &Obj> = BTreeMap::new();
+        for (i, TaggedObjectId(_, obj_id)) in trace.iter().enumerate() {
+            match resident.get(obj_id) {
+                Some(&previous) => {
+                    by_next_use.remove(&previous);
+                }
+                None => {
+                    misses += 1;
+                }
+            }
+            resident.insert(obj_id, next_use[i]);
+            by_next_use.insert(next_use[i], obj_id);
+            if let Some(cache_size) = self.cache_size {
+                while resident.len() > cache_size {
+                    let (_, victim) = by_next_use.pop_last().unwrap();
+                    resident.remove(victim);
+                }
+            }
+        }
+        misses
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trace(objs: &[usize]) -> Vec<TaggedObjectId<usize, usize>> {
+        objs.iter()
+            .map(|&obj_id| TaggedObjectId(0, obj_id))
+            .collect()
+    }
+
+    #[test]
+    fn test_opt_miss_count() {
+        let trace = trace(&[1, 2, 3, 4, 1, 2, 5, 1, 2, 3, 4, 5]);
+        let mut opt = OptCache::new();
+        assert_eq!(opt.miss_count(&trace), 5);
+        assert_eq!(opt.set_capacity(3).miss_count(&trace), 7);
+        assert_eq!(opt.set_capacity(4).miss_count(&trace), 6);
+        assert_eq!(opt.set_capacity(0).miss_count(&trace), 12);
+    }
+}
@@ -0,0 +1,174 @@
+use abstract_cache::ObjIdTraits;
+use std::collections::{BTreeMap, HashMap};
+
+/// Computes the reuse (stack) distance of every access in a stream: the
+/// number of distinct objects touched since the previous access to the same
+/// object. First accesses have no reuse distance.
+///
+/// The trace readers yield `TaggedObjectId`s; feed the object half, e.g.
+/// `tracker.record_all(trace.into_iter().map(|access| access.1))`.
+#[derive(Debug, Clone)]
+pub struct ReuseDistanceTracker<Obj: ObjIdTraits> {
+    //logical time of the latest access to each object
+    last_access: HashMap<Obj, u64>,
+    //marks the values of last_access, so distances are prefix counts
+    access_times: AccessMarks,
+    clock: u64,
+    distances: Vec<Option<usize>>,
+    histogram: BTreeMap<usize, u64>,
+    cold_accesses: u64,
+}
+
+impl<Obj: ObjIdTraits> ReuseDistanceTracker<Obj> {
+    pub fn new() -> Self {
+        ReuseDistanceTracker {
+            last_access: HashMap::new(),
+            access_times: AccessMarks::default(),
+            clock: 0,
+            distances: Vec::new(),
+            histogram: BTreeMap::new(),
+            cold_accesses: 0,
+        }
+    }
+
+    /// Records one access and returns its reuse distance, or None if this is
+    /// the first access to `obj_id`.
+    pub fn record(&mut self, obj_id: Obj) -> Option<usize> {
+        let now = self.clock;
+        self.clock += 1;
+        let distance = match self.last_access.insert(obj_id, now) {
+            Some(previous) => {
+                let previous = previous as usize;
+                let distance = self.access_times.marked_before(now as usize)
+                    - self.access_times.marked_before(previous + 1);
+                self.access_times.unmark(previous);
+                *self.histogram.entry(distance).or_default() += 1;
+                Some(distance)
+            }
+            None => {
+                self.cold_accesses += 1;
+                None
+            }
+        };
+        self.access_times.push_marked();
+        self.distances.push(distance);
+        distance
+    }
+
+    pub fn record_all<I: IntoIterator<Item = Obj>>(&mut self, trace: I) {
+        for obj_id in trace {
+            self.record(obj_id);
+        }
+    }
+
+    /// Reuse distance of every access so far, in trace order.
+    pub fn distances(&self) -> &[Option<usize>] {
+        &self.distances
+    }
+
+    /// How many reuses were seen at each distance. First accesses are
+    /// counted by [`ReuseDistanceTracker::cold_accesses`] instead.
+    pub fn reuse_distance_histogram(&self) -> BTreeMap<usize, u64> {
+        self.histogram.clone()
+    }
+
+    pub fn cold_accesses(&self) -> u64 {
+        self.cold_accesses
+    }
+}
+
+impl<Obj: ObjIdTraits> Default for ReuseDistanceTracker<Obj> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//Fenwick tree over access times, 1 for the times that are still some
+//object's latest access, so each reuse costs O(log n) instead of a scan
+#[derive(Debug, Clone, Default)]
+struct AccessMarks {
+    //tree[i - 1] sums the marks of times [i - lowbit(i), i)
+    tree: Vec<usize>,
+}
+
+impl AccessMarks {
+    //marks the next access time, the one after every time pushed so far
+    fn push_marked(&mut self) {
+        let index = self.tree.len() + 1;
+        let lowbit = index & index.wrapping_neg();
+        //the new node covers the times before it down to index - lowbit
+        let covered = self.marked_before(index - 1) - self.marked_before(index - lowbit);
+        self.tree.push(covered + 1);
+    }
+
+    fn unmark(&mut self, time: usize) {
+        let mut index = time + 1;
+        while index <= self.tree.len() {
+            self.tree[index - 1] -= 1;
+            index += index & index.wrapping_neg();
+        }
+    }
+
+    //number of marked times before end
+    fn marked_before(&self, end: usize) -> usize {
+        let mut marked = 0;
+        let mut index = end;
+        while index > 0 {
+            marked += self.tree[index - 1];
+            index -= index & index.wrapping_neg();
+        }
+        marked
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TaggedObjectId;
+
+    #[test]
+    fn test_reuse_distance() {
+        let mut tracker = ReuseDistanceTracker::new();
+        tracker.record_all(["a", "b", "c", "a"].map(String::from));
+        assert_eq!(tracker.distances(), &[None, None, None, Some(2)]);
+        assert_eq!(tracker.cold_accesses(), 3);
+        assert_eq!(tracker.reuse_distance_histogram(), BTreeMap::from([(2, 1)]));
+    }
+
+    #[test]
+    fn test_reuse_distance_repeated_objects() {
+        //a b b a a: the b in between is counted once, back-to-back reuse is 0
+        let trace = [1, 2, 2, 1, 1].map(|obj_id| TaggedObjectId(0u64, obj_id as u64));
+        let mut tracker = ReuseDistanceTracker::new();
+        tracker.record_all(trace.into_iter().map(|access| access.1));
+        assert_eq!(
+            tracker.distances(),
+            &[None, None, Some(0), Some(1), Some(0)]
+        );
+        assert_eq!(
+            tracker.reuse_distance_histogram(),
+            BTreeMap::from([(0, 2), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn test_reuse_distance_matches_scan() {
+        //check against counting the distinct objects in between directly
+        let trace: Vec<u64> = (0..2000u64).map(|i| (i * 7919) % 37 + i % 5).collect();
+        let mut tracker = ReuseDistanceTracker::new();
+        tracker.record_all(trace.iter().copied());
+        for (now, &obj_id) in trace.iter().enumerate() {
+            let expected =
+                trace[..now]
+                    .iter()
+                    .rposition(|&other| other == obj_id)
+                    .map(|previous| {
+                        trace[previous + 1..now]
+                            .iter()
+                            .collect::<std::collections::HashSet<_>>()
+                            .len()
+                    });
+            assert_eq!(tracker.distances()[now], expected, "access {}", now);
+        }
+    }
+}
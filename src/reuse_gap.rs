@@ -0,0 +1,92 @@
+use abstract_cache::ObjIdTraits;
+use std::collections::{BTreeMap, HashMap};
+
+/// Records the reuse gap of every access in a stream: the number of
+/// accesses, to any object, since the previous access to the same object.
+/// Unlike the reuse distance of [`ReuseDistanceTracker`], repeated accesses
+/// in between all count, so the gap is the lease that would have kept the
+/// object cached under a per-access clock.
+///
+/// [`ReuseDistanceTracker`]: crate::ReuseDistanceTracker
+#[derive(Debug, Clone)]
+pub struct ReuseGapTracker<Obj: ObjIdTraits> {
+    //access index of the latest access to each object
+    last_seen: HashMap<Obj, u64>,
+    //map from object to (sum of its gaps, number of its gaps)
+    gap_totals: HashMap<Obj, (u64, u64)>,
+    clock: u64,
+    histogram: BTreeMap<u64, u64>,
+}
+
+impl<Obj: ObjIdTraits> ReuseGapTracker<Obj> {
+    pub fn new() -> Self {
+        ReuseGapTracker {
+            last_seen: HashMap::new(),
+            gap_totals: HashMap::new(),
+            clock: 0,
+            histogram: BTreeMap::new(),
+        }
+    }
+
+    /// Records one access and returns its reuse gap, or None if this is the
+    /// first access to `obj_id`.
+    pub fn record(&mut self, obj_id: Obj) -> Option<u64> {
+        let now = self.clock;
+        self.clock += 1;
+        let gap = now - self.last_seen.insert(obj_id.clone(), now)?;
+        let (sum, count) = self.gap_totals.entry(obj_id).or_default();
+        *sum += gap;
+        *count += 1;
+        *self.histogram.entry(gap).or_default() += 1;
+        Some(gap)
+    }
+
+    pub fn record_all<I: IntoIterator<Item = Obj>>(&mut self, trace: I) {
+        for obj_id in trace {
+            self.record(obj_id);
+        }
+    }
+
+    /// Mean gap between consecutive accesses to `obj_id`, None if it has not
+    /// been reused yet.
+    pub fn mean_reuse_gap(&self, obj_id: &Obj) -> Option<f64> {
+        self.gap_totals
+            .get(obj_id)
+            .map(|&(sum, count)| sum as f64 / count as f64)
+    }
+
+    /// How many reuses of any object were seen with each gap.
+    pub fn reuse_gap_histogram(&self) -> BTreeMap<u64, u64> {
+        self.histogram.clone()
+    }
+}
+
+impl<Obj: ObjIdTraits> Default for ReuseGapTracker<Obj> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reuse_gaps() {
+        let mut tracker = ReuseGapTracker::new();
+        let gaps: Vec<_> = ["a", "x", "a", "x", "x", "a"]
+            .map(String::from)
+            .into_iter()
+            .map(|obj_id| tracker.record(obj_id))
+            .collect();
+        //a is reused after 2 and then 3 accesses
+        assert_eq!(gaps, vec![None, None, Some(2), Some(2), Some(1), Some(3)]);
+        assert_eq!(tracker.mean_reuse_gap(&"a".to_string()), Some(2.5));
+        assert_eq!(tracker.mean_reuse_gap(&"x".to_string()), Some(1.5));
+        assert_eq!(tracker.mean_reuse_gap(&"b".to_string()), None);
+        assert_eq!(
+            tracker.reuse_gap_histogram(),
+            BTreeMap::from([(1, 1), (2, 2), (3, 1)])
+        );
+    }
+}
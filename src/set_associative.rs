@@ -0,0 +1,194 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+use crate::{LeaseCache, Occupancy, TaggedObjectId, Time};
+
+/// A lease cache split into `num_sets` independent [`LeaseCache`]s of
+/// `ways` objects each. Every object hashes to one set and can only evict,
+/// or be evicted by, objects of the same set.
+///
+/// Leases still count global accesses: a set only sees its own accesses,
+/// so it is caught up to the global clock before each one.
+#[derive(Clone)]
+pub struct SetAssociativeLeaseCache<Obj: ObjIdTraits, T: Time = usize> {
+    sets: Vec<LeaseCache<Obj, T>>,
+    ways: usize,
+    current_time: T,
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits> SetAssociativeLeaseCache<Obj, T> {
+    /// Each set draws its random victims from its own RNG, seeded from
+    /// entropy.
+    pub fn new(num_sets: usize, ways: usize) -> Self {
+        Self::with_sets(num_sets, ways, |_| LeaseCache::new())
+    }
+
+    /// Like [`SetAssociativeLeaseCache::new`], but set `i` is seeded with
+    /// `seed + i`, so runs can be reproduced while the sets still evict
+    /// independently of each other.
+    pub fn with_rng(num_sets: usize, ways: usize, seed: u64) -> Self {
+        Self::with_sets(num_sets, ways, |index| {
+            LeaseCache::with_rng(seed.wrapping_add(index as u64))
+        })
+    }
+
+    fn with_sets(
+        num_sets: usize,
+        ways: usize,
+        new_set: impl Fn(usize) -> LeaseCache<Obj, T>,
+    ) -> Self {
+        assert!(num_sets > 0, "a cache needs at least one set");
+        let sets = (0..num_sets)
+            .map(|index| {
+                let mut set = new_set(index);
+                set.set_capacity(ways);
+                set
+            })
+            .collect();
+        SetAssociativeLeaseCache {
+            sets,
+            ways,
+            current_time: T::ZERO,
+        }
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.sets.len()
+    }
+
+    pub fn ways(&self) -> usize {
+        self.ways
+    }
+
+    /// The set `obj_id` maps to.
+    pub fn set_index(&self, obj_id: &Obj) -> usize {
+        let mut hasher = DefaultHasher::new();
+        obj_id.hash(&mut hasher);
+        (hasher.finish() % self.sets.len() as u64) as usize
+    }
+
+    /// The set at `index`, e.g. for reading its contents or counters.
+    pub fn set(&self, index: usize) -> &LeaseCache<Obj, T> {
+        &self.sets[index]
+    }
+
+    pub fn contains(&self, obj_id: &Obj) -> bool {
+        self.sets[self.set_index(obj_id)].contains(obj_id)
+    }
+
+    pub fn hit_count(&self) -> u64 {
+        self.sets.iter().map(LeaseCache::hit_count).sum()
+    }
+
+    pub fn miss_count(&self) -> u64 {
+        self.sets.iter().map(LeaseCache::miss_count).sum()
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits> CacheSim<TaggedObjectId<T, Obj>>
+    for SetAssociativeLeaseCache<Obj, T>
+{
+    fn cache_access(&mut self, access: TaggedObjectId<T, Obj>) -> AccessResult {
+        let index = self.set_index(&access.1);
+        let set = &mut self.sets[index];
+        set.advance_time_to(self.current_time);
+        self.current_time = self.current_time.saturating_add(T::ONE);
+        set.cache_access(access)
+    }
+
+    /// Spreads `cache_size` evenly over the sets, rounding the ways down but
+    /// keeping at least one way per set.
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.ways = (cache_size / self.sets.len()).max(1);
+        for set in &mut self.sets {
+            set.set_capacity(self.ways);
+        }
+        self
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time> Occupancy for SetAssociativeLeaseCache<Obj, T> {
+    fn occupancy(&self) -> usize {
+        self.sets.iter().map(LeaseCache::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sets_compete_separately() {
+        let mut lease_cache = SetAssociativeLeaseCache::<usize>::new(2, 1);
+        let a = 0;
+        let b = (1..)
+            .find(|obj_id| lease_cache.set_index(obj_id) == lease_cache.set_index(&a))
+            .unwrap();
+        let c = (1..)
+            .find(|obj_id| lease_cache.set_index(obj_id) != lease_cache.set_index(&a))
+            .unwrap();
+
+        lease_cache.cache_access(TaggedObjectId(10, a));
+        lease_cache.cache_access(TaggedObjectId(10, c));
+        assert!(lease_cache.contains(&a) && lease_cache.contains(&c));
+        //b shares a's only way, so one of them has to go
+        lease_cache.cache_access(TaggedObjectId(10, b));
+        assert!(lease_cache.contains(&a) != lease_cache.contains(&b));
+        assert!(lease_cache.contains(&c));
+        assert_eq!(lease_cache.occupancy(), 2);
+    }
+
+    #[test]
+    fn test_leases_follow_global_clock() {
+        let mut lease_cache = SetAssociativeLeaseCache::<usize>::new(4, 8);
+        lease_cache.cache_access(TaggedObjectId(3, 0));
+        //accesses to other sets still age object 0
+        let mut other = 1;
+        for _ in 0..2 {
+            other = (other + 1..)
+                .find(|obj_id| lease_cache.set_index(obj_id) != lease_cache.set_index(&0))
+                .unwrap();
+            lease_cache.cache_access(TaggedObjectId(0, other));
+        }
+        assert!(matches!(
+            lease_cache.cache_access(TaggedObjectId(3, 0)),
+            AccessResult::Hit
+        ));
+        for _ in 0..3 {
+            lease_cache.cache_access(TaggedObjectId(0, other));
+        }
+        assert!(matches!(
+            lease_cache.cache_access(TaggedObjectId(3, 0)),
+            AccessResult::Miss
+        ));
+        assert_eq!(lease_cache.hit_count(), 1);
+        assert_eq!(lease_cache.miss_count(), 7);
+    }
+
+    #[test]
+    fn test_sets_seeded_independently() {
+        use rand::Rng;
+
+        let lease_cache = SetAssociativeLeaseCache::<usize>::with_rng(4, 2, 7);
+        let draws: Vec<usize> = (0..4)
+            .map(|index| lease_cache.set(index).rng.clone().gen_range(0..1 << 30))
+            .collect();
+        for (i, draw) in draws.iter().enumerate() {
+            assert!(!draws[i + 1..].contains(draw), "{:?}", draws);
+        }
+        let again = SetAssociativeLeaseCache::<usize>::with_rng(4, 2, 7);
+        assert_eq!(again.set(3).rng.clone().gen_range(0..1 << 30), draws[3]);
+    }
+
+    #[test]
+    fn test_capacity_below_num_sets() {
+        let mut lease_cache = SetAssociativeLeaseCache::<usize>::new(8, 4);
+        lease_cache.set_capacity(3);
+        assert_eq!(lease_cache.ways(), 1);
+        lease_cache.cache_access(TaggedObjectId(10, 0));
+        assert!(lease_cache.contains(&0));
+    }
+}
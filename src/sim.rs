@@ -0,0 +1,386 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::file_reader::{lease_to_map, trace_to_vec_u64, TraceError};
+use crate::{CacheHasher, LeaseAssignmentCache, LeaseCache, TaggedObjectId, Time};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Summary of one trace replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimStats {
+    pub accesses: u64,
+    pub hits: u64,
+    pub misses: u64,
+    /// misses / accesses, or 0.0 for an empty trace
+    pub miss_ratio: f64,
+    /// objects left in the cache after the last access
+    pub final_occupancy: usize,
+}
+
+impl SimStats {
+    //fills in accesses and the miss ratio from the counts of one replay
+    pub(crate) fn from_counts(hits: u64, misses: u64, final_occupancy: usize) -> Self {
+        let accesses = hits + misses;
+        SimStats {
+            accesses,
+            hits,
+            misses,
+            miss_ratio: match accesses {
+                0 => 0.0,
+                _ => misses as f64 / accesses as f64,
+            },
+            final_occupancy,
+        }
+    }
+}
+
+/// Caches that can report how many objects they currently hold, so
+/// [`run_trace`] can include it in its [`SimStats`].
+pub trait Occupancy {
+    fn occupancy(&self) -> usize;
+}
+
+impl<Obj: ObjIdTraits, T: Time, S: CacheHasher> Occupancy for LeaseCache<Obj, T, S> {
+    fn occupancy(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Feeds every access of `trace` through [`CacheSim::cache_access`] and
+/// tallies the results.
+pub fn run_trace<A, C, I>(cache: &mut C, trace: I) -> SimStats
+where
+    A: ObjIdTraits,
+    C: CacheSim<A> + Occupancy,
+    I: IntoIterator<Item = A>,
+{
+    let mut hits = 0;
+    let mut misses = 0;
+    for access in trace {
+        match cache.cache_access(access) {
+            AccessResult::Hit => hits += 1,
+            AccessResult::Miss => misses += 1,
+        }
+    }
+    SimStats::from_counts(hits, misses, cache.occupancy())
+}
+
+/// Like [`run_trace`], but each access carries the absolute time it
+/// happens at, as read by
+/// [`timed_trace_to_vec_with_config`](crate::file_reader::timed_trace_to_vec_with_config).
+/// The clock is moved to that time with [`LeaseCache::advance_time_to`]
+/// before the access, see [`LeaseCache::update_at`], instead of ticking once
+/// per access, so leases are measured in trace time. A timestamp behind the
+/// clock does not move it.
+pub fn run_timed_trace<Obj, T, S, I>(cache: &mut LeaseCache<Obj, T, S>, trace: I) -> SimStats
+where
+    Obj: ObjIdTraits,
+    T: Time + ObjIdTraits,
+    S: CacheHasher,
+    I: IntoIterator<Item = (T, TaggedObjectId<T, Obj>)>,
+{
+    let mut hits = 0;
+    let mut misses = 0;
+    for (timestamp, TaggedObjectId(lease, obj_id)) in trace {
+        match cache.update_at(&obj_id, lease, timestamp) {
+            AccessResult::Hit => hits += 1,
+            AccessResult::Miss => misses += 1,
+        }
+        cache.enforce_capacity();
+    }
+    SimStats::from_counts(hits, misses, cache.occupancy())
+}
+
+/// Hit and miss counts broken down by the tag of each access, e.g. by
+/// reference when replaying through a
+/// [`LeaseAssignmentCache`](crate::LeaseAssignmentCache).
+#[derive(Debug, Clone)]
+pub struct ReferenceStats<Tag: ObjIdTraits> {
+    //map from tag to (hits, misses)
+    stats: HashMap<Tag, (u64, u64)>,
+}
+
+impl<Tag: ObjIdTraits> ReferenceStats<Tag> {
+    pub fn new() -> Self {
+        ReferenceStats {
+            stats: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, reference: Tag, result: &AccessResult) {
+        let (hits, misses) = self.stats.entry(reference).or_default();
+        match result {
+            AccessResult::Hit => *hits += 1,
+            AccessResult::Miss => *misses += 1,
+        }
+    }
+
+    /// `(hits, misses)` of every tag seen so far.
+    pub fn per_reference_stats(&self) -> &HashMap<Tag, (u64, u64)> {
+        &self.stats
+    }
+
+    /// Fraction of the accesses tagged `reference` that missed, None if
+    /// there were none.
+    pub fn miss_ratio(&self, reference: &Tag) -> Option<f64> {
+        self.stats
+            .get(reference)
+            .map(|&(hits, misses)| misses as f64 / (hits + misses) as f64)
+    }
+}
+
+impl<Tag: ObjIdTraits> Default for ReferenceStats<Tag> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Like [`run_trace`], but tallies hits and misses per access tag.
+pub fn run_trace_by_reference<Tag, Obj, C, I>(cache: &mut C, trace: I) -> ReferenceStats<Tag>
+where
+    Tag: ObjIdTraits,
+    Obj: ObjIdTraits,
+    C: CacheSim<TaggedObjectId<Tag, Obj>>,
+    I: IntoIterator<Item = TaggedObjectId<Tag, Obj>>,
+{
+    let mut stats = ReferenceStats::new();
+    for access in trace {
+        let reference = access.0.clone();
+        let result = cache.cache_access(access);
+        stats.record(reference, &result);
+    }
+    stats
+}
+
+/// Like [`run_trace`], but the first `warmup` accesses only fill the cache
+/// and are left out of the returned [`SimStats`].
+pub fn run_trace_with_warmup<A, C, I>(cache: &mut C, trace: I, warmup: usize) -> SimStats
+where
+    A: ObjIdTraits,
+    C: CacheSim<A> + Occupancy,
+    I: IntoIterator<Item = A>,
+{
+    let mut trace = trace.into_iter();
+    for access in trace.by_ref().take(warmup) {
+        cache.cache_access(access);
+    }
+    run_trace(cache, trace)
+}
+
+/// Replays the raw trace at `trace_path` through a
+/// [`LeaseAssignmentCache`] of `capacity` objects, drawing the lease of
+/// every access from the lease table at `table_path`, with the short/long
+/// draws and evictions seeded from `seed`. Both files are read as described
+/// in [`file_reader`](crate::file_reader); references missing from the
+/// table are not cached.
+pub fn simulate_with_table(
+    table_path: impl AsRef<Path>,
+    trace_path: impl AsRef<Path>,
+    capacity: usize,
+    seed: u64,
+) -> Result<SimStats, TraceError> {
+    let lease_table = lease_to_map(table_path)?;
+    let trace = trace_to_vec_u64(trace_path)?;
+    let mut lease_cache = LeaseAssignmentCache::<u64, u64>::with_rng(lease_table, seed);
+    lease_cache.set_capacity(capacity);
+    Ok(run_trace(&mut lease_cache, trace))
+}
+
+/// Miss ratio of a `LeaseCache` replaying `trace` at each of `capacities`,
+/// as `(capacity, miss_ratio)` pairs in the order given. One cache is
+/// reset and reused for every run, so its maps are only allocated once, and
+/// every run draws its force evictions from an RNG seeded with `seed`.
+pub fn miss_ratio_curve<Obj, T>(
+    trace: &[TaggedObjectId<T, Obj>],
+    capacities: &[usize],
+    seed: u64,
+) -> Vec<(usize, f64)>
+where
+    Obj: ObjIdTraits,
+    T: Time + ObjIdTraits,
+{
+    let mut lease_cache = LeaseCache::<Obj, T>::with_rng(seed);
+    capacities
+        .iter()
+        .map(|&capacity| {
+            lease_cache.reset();
+            lease_cache.set_capacity(capacity);
+            let stats = run_trace(&mut lease_cache, trace.iter().cloned());
+            (capacity, stats.miss_ratio)
+        })
+        .collect()
+}
+
+/// [`miss_ratio_curve`] with one capacity per rayon worker. Each run gets
+/// its own cache seeded with `seed`, so for the same seed the results match
+/// the serial version's.
+#[cfg(feature = "rayon")]
+pub fn miss_ratio_curve_parallel<Obj, T>(
+    trace: &[TaggedObjectId<T, Obj>],
+    capacities: &[usize],
+    seed: u64,
+) -> Vec<(usize, f64)>
+where
+    Obj: ObjIdTraits + Send + Sync,
+    T: Time + ObjIdTraits + Send + Sync,
+{
+    capacities
+        .par_iter()
+        .map(|&capacity| {
+            let mut lease_cache = LeaseCache::<Obj, T>::with_rng(seed);
+            lease_cache.set_capacity(capacity);
+            let stats = run_trace(&mut lease_cache, trace.iter().cloned());
+            (capacity, stats.miss_ratio)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_trace() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        lease_cache.set_capacity(10);
+        //a b a b with lease 3 covers both reuses, c gets lease 0 and never stays
+        let trace = vec![
+            TaggedObjectId(3, 1),
+            TaggedObjectId(3, 2),
+            TaggedObjectId(3, 1),
+            TaggedObjectId(3, 2),
+            TaggedObjectId(0, 3),
+            TaggedObjectId(0, 3),
+        ];
+        let stats = run_trace(&mut lease_cache, trace);
+        assert_eq!(
+            stats,
+            SimStats {
+                accesses: 6,
+                hits: 2,
+                misses: 4,
+                miss_ratio: 4.0 / 6.0,
+                final_occupancy: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_run_trace_empty() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let stats = run_trace(&mut lease_cache, Vec::<TaggedObjectId<usize, usize>>::new());
+        assert_eq!(stats.accesses, 0);
+        assert_eq!(stats.miss_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_miss_ratio_curve_non_increasing() {
+        //cycle over 8 objects, each leased exactly until its next use
+        let trace: Vec<TaggedObjectId<usize, usize>> =
+            (0..800).map(|i| TaggedObjectId(8, i % 8)).collect();
+        let curve = miss_ratio_curve(&trace, &[0, 2, 4, 8, 16], 0);
+        assert_eq!(curve.len(), 5);
+        assert_eq!(curve[0], (0, 1.0));
+        assert_eq!(curve[3], (8, 8.0 / 800.0));
+        for pair in curve.windows(2) {
+            assert!(pair[1].1 <= pair[0].1, "{:?}", curve);
+        }
+    }
+
+    #[test]
+    fn test_run_trace_with_warmup() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        let trace: Vec<TaggedObjectId<usize, usize>> =
+            (0..10).map(|i| TaggedObjectId(5, i % 5)).collect();
+        let stats = run_trace_with_warmup(&mut lease_cache, trace, 5);
+        assert_eq!(stats.accesses, 5);
+        assert_eq!(stats.hits, 5);
+        assert_eq!(stats.final_occupancy, 5);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_miss_ratio_curve_parallel_matches_serial() {
+        //leases never overlap past capacity, so no random eviction happens
+        let trace: Vec<TaggedObjectId<usize, usize>> =
+            (0..600).map(|i| TaggedObjectId(6, i % 6)).collect();
+        let capacities = [6, 8, 12, 100];
+        assert_eq!(
+            miss_ratio_curve_parallel(&trace, &capacities, 0),
+            miss_ratio_curve(&trace, &capacities, 0)
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_miss_ratio_curve_parallel_matches_serial_under_pressure() {
+        //every lease outlives the working set, so small capacities force evictions
+        let trace: Vec<TaggedObjectId<usize, usize>> =
+            (0..1000).map(|i| TaggedObjectId(40, i % 10)).collect();
+        let capacities = [1, 3, 5, 7, 9, 10];
+        let serial = miss_ratio_curve(&trace, &capacities, 42);
+        assert!(serial
+            .iter()
+            .any(|&(_, miss_ratio)| miss_ratio > 0.01 && miss_ratio < 1.0));
+        assert_eq!(miss_ratio_curve_parallel(&trace, &capacities, 42), serial);
+    }
+
+    #[test]
+    fn test_miss_ratio_curve_seeded() {
+        let trace: Vec<TaggedObjectId<usize, usize>> =
+            (0..1000).map(|i| TaggedObjectId(40, i % 10)).collect();
+        let capacities = [3, 7, 3];
+        let curve = miss_ratio_curve(&trace, &capacities, 42);
+        assert_eq!(curve, miss_ratio_curve(&trace, &capacities, 42));
+        //reset re-seeds the RNG, so a repeated capacity evicts the same objects
+        assert_eq!(curve[0], curve[2]);
+    }
+
+    #[test]
+    fn test_run_timed_trace() {
+        let mut lease_cache = LeaseCache::<usize>::new();
+        //a lease of 10 covers ten ticks of trace time, however few accesses
+        let trace = vec![
+            (1, TaggedObjectId(10, 1)),
+            (2, TaggedObjectId(10, 2)),
+            (11, TaggedObjectId(10, 1)),
+            (100, TaggedObjectId(10, 2)),
+            (101, TaggedObjectId(10, 2)),
+        ];
+        let stats = run_timed_trace(&mut lease_cache, trace);
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 3);
+        assert_eq!(lease_cache.current_time(), 101);
+        //1 expired at 21 although only two more accesses followed
+        assert!(!lease_cache.contains(&1));
+        //replayed per access, the same leases would have kept 2 cached
+        let untimed: Vec<_> = [1, 2, 1, 2, 2]
+            .into_iter()
+            .map(|obj_id| TaggedObjectId(10, obj_id))
+            .collect();
+        assert_eq!(run_trace(&mut LeaseCache::<usize>::new(), untimed).hits, 3);
+    }
+
+    #[test]
+    fn test_run_trace_by_reference() {
+        //reference 1 keeps its object cached, reference 2 has no lease
+        let lease_table = HashMap::from([(1u64, (10, 10, 1.0))]);
+        let mut lease_cache = crate::LeaseAssignmentCache::<u64, usize>::with_rng(lease_table, 0);
+        lease_cache.set_capacity(10);
+        let trace = (0..10).map(|i| match i % 2 {
+            0 => TaggedObjectId(1, 100),
+            _ => TaggedObjectId(2, 200),
+        });
+        let stats = run_trace_by_reference(&mut lease_cache, trace);
+        assert_eq!(
+            *stats.per_reference_stats(),
+            HashMap::from([(1, (4, 1)), (2, (0, 5))])
+        );
+        assert_eq!(stats.miss_ratio(&2), Some(1.0));
+        assert_eq!(stats.miss_ratio(&3), None);
+    }
+}
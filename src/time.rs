@@ -0,0 +1,52 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, Rem, Sub};
+
+/// Bound alias for the type [`LeaseCache`](crate::LeaseCache) uses for its
+/// clock, leases and expiration times. Implemented for the unsigned
+/// integer types; `usize` is the default.
+pub trait Time:
+    Copy + Ord + Hash + Debug + Default + Add<Output = Self> + Sub<Output = Self> + Rem<Output = Self>
+{
+    const ZERO: Self;
+    /// length of one clock tick
+    const ONE: Self;
+    const MAX: Self;
+
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    /// lossless widening, for accumulating tick counts
+    fn as_u128(self) -> u128;
+    /// narrowing back from `as_u128`, saturating at `MAX`
+    fn saturating_from_u128(value: u128) -> Self;
+}
+
+macro_rules! impl_time {
+    ($($t:ty),*) => {
+        $(
+            impl Time for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+
+                fn saturating_add(self, rhs: Self) -> Self {
+                    <$t>::saturating_add(self, rhs)
+                }
+
+                fn saturating_sub(self, rhs: Self) -> Self {
+                    <$t>::saturating_sub(self, rhs)
+                }
+
+                fn as_u128(self) -> u128 {
+                    self as u128
+                }
+
+                fn saturating_from_u128(value: u128) -> Self {
+                    <$t>::try_from(value).unwrap_or(<$t>::MAX)
+                }
+            }
+        )*
+    };
+}
+
+impl_time!(u8, u16, u32, u64, u128, usize);
@@ -0,0 +1,76 @@
+use abstract_cache::AccessResult;
+use abstract_cache::CacheSim;
+use abstract_cache::ObjIdTraits;
+
+use crate::{LeaseCache, Occupancy, TaggedObjectId, Time};
+
+/// Wraps a [`LeaseCache`] and gives every access the same lease, so a plain
+/// trace of object ids can be replayed without tagging each access.
+#[derive(Clone)]
+pub struct UniformLeaseCache<Obj: ObjIdTraits, T: Time = usize> {
+    lease: T,
+    cache: LeaseCache<Obj, T>,
+}
+
+impl<Obj: ObjIdTraits, T: Time> UniformLeaseCache<Obj, T> {
+    pub fn new(lease: T) -> Self {
+        UniformLeaseCache {
+            lease,
+            cache: LeaseCache::new(),
+        }
+    }
+
+    pub fn lease(&self) -> T {
+        self.lease
+    }
+
+    pub fn set_lease(&mut self, lease: T) -> &mut Self {
+        self.lease = lease;
+        self
+    }
+
+    /// The underlying cache, e.g. for reading its hit/miss counters.
+    pub fn cache(&self) -> &LeaseCache<Obj, T> {
+        &self.cache
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time + ObjIdTraits> CacheSim<Obj> for UniformLeaseCache<Obj, T> {
+    fn cache_access(&mut self, obj_id: Obj) -> AccessResult {
+        self.cache.cache_access(TaggedObjectId(self.lease, obj_id))
+    }
+
+    fn set_capacity(&mut self, cache_size: usize) -> &mut Self {
+        self.cache.set_capacity(cache_size);
+        self
+    }
+}
+
+impl<Obj: ObjIdTraits, T: Time> Occupancy for UniformLeaseCache<Obj, T> {
+    fn occupancy(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::run_trace;
+
+    #[test]
+    fn test_uniform_matches_tagged() {
+        let trace: Vec<usize> = vec![1, 2, 1, 3, 4, 1, 2, 5, 5, 3, 1];
+        let mut uniform = UniformLeaseCache::<usize>::new(3);
+        uniform.set_capacity(100);
+        let uniform_stats = run_trace(&mut uniform, trace.clone());
+
+        let mut tagged = LeaseCache::<usize>::new();
+        tagged.set_capacity(100);
+        let tagged_stats = run_trace(
+            &mut tagged,
+            trace.into_iter().map(|obj_id| TaggedObjectId(3, obj_id)),
+        );
+        assert_eq!(uniform_stats, tagged_stats);
+        assert_eq!(uniform.cache().hit_count(), tagged.hit_count());
+    }
+}
@@ -0,0 +1,88 @@
+use abstract_cache::ObjIdTraits;
+use std::collections::{HashMap, VecDeque};
+
+/// Tracks the working-set size of a stream: the number of distinct objects
+/// among the last `window` accesses, along with the largest it has been.
+///
+/// Like [`ReuseDistanceTracker`](crate::ReuseDistanceTracker), it takes the
+/// object half of the `TaggedObjectId`s the trace readers yield.
+#[derive(Debug, Clone)]
+pub struct WorkingSetTracker<Obj: ObjIdTraits> {
+    window: usize,
+    //the accesses in the window, oldest first
+    recent: VecDeque<Obj>,
+    //map from object to its number of accesses in the window
+    counts: HashMap<Obj, usize>,
+    max_distinct: usize,
+}
+
+impl<Obj: ObjIdTraits> WorkingSetTracker<Obj> {
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "window must hold at least one access");
+        WorkingSetTracker {
+            window,
+            recent: VecDeque::with_capacity(window),
+            counts: HashMap::new(),
+            max_distinct: 0,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Records one access, sliding the oldest one out of a full window, and
+    /// returns the working-set size after it.
+    pub fn record(&mut self, obj_id: Obj) -> usize {
+        if self.recent.len() == self.window {
+            let oldest = self.recent.pop_front().unwrap();
+            let count = self.counts.get_mut(&oldest).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&oldest);
+            }
+        }
+        *self.counts.entry(obj_id.clone()).or_default() += 1;
+        self.recent.push_back(obj_id);
+        self.max_distinct = self.max_distinct.max(self.counts.len());
+        self.counts.len()
+    }
+
+    /// Records every access of `trace` and returns the working-set size
+    /// after each one, in trace order.
+    pub fn record_all<I: IntoIterator<Item = Obj>>(&mut self, trace: I) -> Vec<usize> {
+        trace
+            .into_iter()
+            .map(|obj_id| self.record(obj_id))
+            .collect()
+    }
+
+    /// Number of distinct objects in the current window.
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The largest working-set size seen after any access so far.
+    pub fn max_distinct(&self) -> usize {
+        self.max_distinct
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_working_set() {
+        let mut tracker = WorkingSetTracker::new(3);
+        assert_eq!(
+            tracker.record_all(["a", "b", "a", "c"].map(String::from)),
+            vec![1, 2, 2, 3]
+        );
+        assert_eq!(tracker.distinct(), 3);
+        assert_eq!(tracker.max_distinct(), 3);
+        //the window is now a c c
+        assert_eq!(tracker.record("c".to_string()), 2);
+        assert_eq!(tracker.max_distinct(), 3);
+    }
+}
@@ -0,0 +1,34 @@
+//! End-to-end replay of the fixture trace with the fixture lease table.
+use lease_cache_sim::file_reader::TraceError;
+use lease_cache_sim::simulate_with_table;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+#[test]
+fn test_simulate_with_table() {
+    //every reuse in the trace falls within both leases of its reference
+    let stats =
+        simulate_with_table(fixture("lease_table.csv"), fixture("trace.csv"), 8, 0).unwrap();
+    assert_eq!(stats.accesses, 6);
+    assert_eq!(stats.hits, 3);
+    assert_eq!(stats.miss_ratio, 0.5);
+    assert_eq!(stats.final_occupancy, 3);
+    //the same seed gives the same result under capacity pressure
+    let squeezed = |seed| {
+        simulate_with_table(fixture("lease_table.csv"), fixture("trace.csv"), 1, seed).unwrap()
+    };
+    assert_eq!(squeezed(7), squeezed(7));
+    assert!(squeezed(7).final_occupancy <= 1);
+}
+
+#[test]
+fn test_simulate_with_missing_table() {
+    let result = simulate_with_table(fixture("missing.csv"), fixture("trace.csv"), 8, 0);
+    assert!(matches!(result, Err(TraceError::Io(_))));
+}